@@ -0,0 +1,82 @@
+// Calibration of strength levels
+//
+// Plays each level against the baseline players (random and greedy) without a window, so the
+// levels can be checked to get stronger step by step and compared to players of known strength.
+
+use crate::board::board::START_BOARD;
+use crate::ai::player::{AnyPlayer, MinMaxPlayer};
+use crate::ai::level::Level;
+use crate::ai::tournament::{MatchSettings, run_match};
+use crate::game::clock::TimeControl;
+use crate::game::session::TimeoutPolicy;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    // sum of the final disc differences, from the calibrated player's point of view
+    pub discs: i32,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // wins count 1 and draws 1/2, in percent of the games played
+    pub fn percentage(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        100.0 * (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    pub(crate) fn add_game(&mut self, discs: i32) {
+        if discs > 0 {
+            self.wins += 1;
+        } else if discs < 0 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+        self.discs += discs;
+    }
+}
+
+impl fmt::Display for MatchScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "+{} ={} -{} ({:.1}%, {:+} discs)", self.wins, self.draws, self.losses, self.percentage(), self.discs)
+    }
+}
+
+pub fn calibrate(level: Level, opponent: &AnyPlayer, games: u32, time: TimeControl) -> MatchScore {
+    // Plays `games` games between `level` and `opponent` from the start position, alternating
+    // colours, with `time` for each side. Running out of time loses the game.
+    let settings = MatchSettings {
+        games: games,
+        concurrency: 1,
+        time: time,
+        timeout_policy: TimeoutPolicy::Lose,
+        openings: vec![START_BOARD],
+        adjudicate_empties: 0,
+        sprt: None,
+    };
+    let engine = || AnyPlayer::MinMax(MinMaxPlayer::new().with_level(level).quiet());
+    run_match(&settings, engine, || opponent.clone(), |_, _, _, _| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::player::RandomPlayer;
+    use std::time::Duration;
+
+    #[test]
+    fn test_score_counts_games() {
+        let score = calibrate(Level::new(1).unwrap(), &AnyPlayer::Random(RandomPlayer), 2, TimeControl::new(Duration::from_secs(5)));
+        assert_eq!(score.games(), 2);
+    }
+}
@@ -0,0 +1,173 @@
+// Evaluators score the leaves of the search.
+//
+// An evaluator returns a score from the point of view of the side to move, so a positive
+// score is good for whoever is about to play. Heuristic scores are in centi-discs (see
+// `MinMaxEval`). A finished game has no side to move and is scored from black's point of view.
+//
+// The search calls `push` before it descends into a child position and `pop` when it comes
+// back up. Evaluators that keep incremental state (pattern counts, disc counts, ...) can use
+// these hooks instead of recomputing everything in `evaluate`.
+
+use crate::board::board::{Board, Ply, Player};
+use crate::ai::minmax::MinMaxEval;
+use crate::ai::static_evaluation::{static_eval, final_eval};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+pub trait Evaluator {
+    fn evaluate(&self, board: &Board) -> MinMaxEval;
+
+    fn push(&mut self, _board: &Board, _ply: Ply) {
+
+    }
+
+    fn pop(&mut self) {
+
+    }
+}
+
+// The hand written heuristic in `static_evaluation`: mobility, stable discs and x-squares.
+#[derive(Clone, Copy, Default)]
+pub struct HeuristicEvaluator;
+
+// Counts discs and nothing else.
+#[derive(Clone, Copy, Default)]
+pub struct DiscCountEvaluator;
+
+// Adds noise of up to `amplitude` centi-discs to the heuristic scores of another evaluator,
+// to make the engine weaker. The noise is a hash of the position, so within one search the
+// same position always gets the same score; `seed` changes it from search to search.
+#[derive(Clone, Copy)]
+pub struct NoisyEvaluator<E: Evaluator> {
+    evaluator: E,
+    amplitude: i32,
+    seed: u64,
+}
+
+// One of the evaluators that ship with revello, chosen at runtime (for example from the CLI).
+#[derive(Clone, Copy)]
+pub enum AnyEvaluator {
+    Heuristic(HeuristicEvaluator),
+    DiscCount(DiscCountEvaluator),
+}
+
+impl Evaluator for HeuristicEvaluator {
+    fn evaluate(&self, board: &Board) -> MinMaxEval {
+        let eval = static_eval(board).eval;
+        match board.turn {
+            Some(player) => eval.for_player(player),
+            None => eval,
+        }
+    }
+}
+
+impl Evaluator for DiscCountEvaluator {
+    fn evaluate(&self, board: &Board) -> MinMaxEval {
+        match board.turn {
+            None => final_eval(board).eval,
+            Some(Player::Black) => MinMaxEval::heuristic(100 * (board.count_black() - board.count_white())),
+            Some(Player::White) => MinMaxEval::heuristic(100 * (board.count_white() - board.count_black())),
+        }
+    }
+}
+
+impl<E: Evaluator> NoisyEvaluator<E> {
+    pub fn new(evaluator: E, amplitude: i32, seed: u64) -> Self {
+        NoisyEvaluator {
+            evaluator: evaluator,
+            amplitude: amplitude,
+            seed: seed,
+        }
+    }
+}
+
+impl<E: Evaluator> Evaluator for NoisyEvaluator<E> {
+    fn evaluate(&self, board: &Board) -> MinMaxEval {
+        let eval = self.evaluator.evaluate(board);
+        if self.amplitude <= 0 || eval.is_proven() {
+            return eval;
+        }
+        let mut hasher = DefaultHasher::new();
+        (board, self.seed).hash(&mut hasher);
+        let noise = (hasher.finish() % (2 * self.amplitude as u64 + 1)) as i32 - self.amplitude;
+        MinMaxEval::heuristic(eval.centi_discs().saturating_add(noise))
+    }
+
+    fn push(&mut self, board: &Board, ply: Ply) {
+        self.evaluator.push(board, ply);
+    }
+
+    fn pop(&mut self) {
+        self.evaluator.pop();
+    }
+}
+
+impl Default for AnyEvaluator {
+    fn default() -> Self {
+        AnyEvaluator::Heuristic(HeuristicEvaluator)
+    }
+}
+
+impl Evaluator for AnyEvaluator {
+    fn evaluate(&self, board: &Board) -> MinMaxEval {
+        match self {
+            AnyEvaluator::Heuristic(evaluator) => evaluator.evaluate(board),
+            AnyEvaluator::DiscCount(evaluator) => evaluator.evaluate(board),
+        }
+    }
+
+    fn push(&mut self, board: &Board, ply: Ply) {
+        match self {
+            AnyEvaluator::Heuristic(evaluator) => evaluator.push(board, ply),
+            AnyEvaluator::DiscCount(evaluator) => evaluator.push(board, ply),
+        }
+    }
+
+    fn pop(&mut self) {
+        match self {
+            AnyEvaluator::Heuristic(evaluator) => evaluator.pop(),
+            AnyEvaluator::DiscCount(evaluator) => evaluator.pop(),
+        }
+    }
+}
+
+pub fn evaluate_for_black<E: Evaluator>(evaluator: &E, board: &Board) -> MinMaxEval {
+    // The search maximizes for black, so scores are turned around when white is to move
+    let eval = evaluator.evaluate(board);
+    match board.turn {
+        Some(player) => eval.for_player(player),
+        None => eval,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::{START_BOARD, possible_plys, play};
+
+    #[test]
+    fn test_scores_are_relative_to_side_to_move() {
+        let ply = possible_plys(&START_BOARD).into_iter().next().unwrap();
+        let board = play(&START_BOARD, ply);
+
+        // black is a disc up after the first move and white is to move
+        assert_eq!(DiscCountEvaluator.evaluate(&board), MinMaxEval::heuristic(-300));
+        assert_eq!(evaluate_for_black(&DiscCountEvaluator, &board), MinMaxEval::heuristic(300));
+        assert_eq!(
+            evaluate_for_black(&HeuristicEvaluator, &board),
+            static_eval(&board).eval,
+        );
+    }
+
+    #[test]
+    fn test_noise_is_bounded_and_repeatable() {
+        let noisy = NoisyEvaluator::new(DiscCountEvaluator, 50, 7);
+        for ply in possible_plys(&START_BOARD) {
+            let board = play(&START_BOARD, ply);
+            let exact = DiscCountEvaluator.evaluate(&board).centi_discs();
+            let noise = noisy.evaluate(&board).centi_discs() - exact;
+            assert!((-50..=50).contains(&noise));
+            assert_eq!(noisy.evaluate(&board).centi_discs() - exact, noise);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Another engine, run as a child process and spoken to with the NBoard protocol.
+//
+// Before each move the engine is sent the position as a GGF game (`set game`), with the time
+// left of the side to move in the TB or TW tag, and is asked for a move with `go`. If the
+// engine does not answer in time, exits, or answers an illegal move, the player forfeits and
+// the engine is stopped. Matches build a new player, and so a new process, for every game.
+
+use crate::ai::player::Player;
+use crate::board::board::{Board, Ply, Player as BoardPlayer, possible_plys};
+use crate::formats::ggf::{format_board, parse_move};
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how long the engine may take to start and answer the handshake
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct ExternalEnginePlayer {
+    // shared by the clones that think on other threads
+    engine: Arc<Mutex<EngineProcess>>,
+    name: String,
+}
+
+struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    // lines of the engine's stdout, read on their own thread so reads can time out
+    lines: Receiver<String>,
+    // set once the engine has failed, it forfeits every move after that
+    stopped: bool,
+}
+
+impl EngineProcess {
+    fn spawn(command: &str) -> Result<Self, String> {
+        let mut words = command.split_whitespace();
+        let program = words.next().ok_or("empty engine command")?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("could not start {}: {}", program, e))?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(EngineProcess {
+            child: child,
+            stdin: stdin,
+            lines: lines,
+            stopped: false,
+        })
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| "the engine exited".to_string())
+    }
+
+    fn receive(&mut self, deadline: Instant) -> Result<String, String> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match self.lines.recv_timeout(timeout) {
+            Ok(line) => Ok(line),
+            Err(RecvTimeoutError::Timeout) => Err("the engine ran out of time".to_string()),
+            Err(RecvTimeoutError::Disconnected) => Err("the engine exited".to_string()),
+        }
+    }
+
+    fn handshake(&mut self) -> Result<Option<String>, String> {
+        // Returns the name the engine gives itself
+        self.send("nboard 2")?;
+        self.send("ping 1")?;
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        let mut name = None;
+        loop {
+            let line = self.receive(deadline)?;
+            if let Some(myname) = line.strip_prefix("set myname ") {
+                name = Some(myname.trim().to_string());
+            }
+            if line.trim() == "pong 1" {
+                return Ok(name);
+            }
+        }
+    }
+
+    fn ask_move(&mut self, board: &Board, time_left: Duration) -> Result<Ply, String> {
+        if self.stopped {
+            return Err("the engine was stopped".to_string());
+        }
+        let turn = board.turn.ok_or("the game is over")?;
+        let deadline = Instant::now() + time_left;
+
+        let seconds = time_left.as_secs();
+        let clock = format!("{}[{}:{:02}]", if turn == BoardPlayer::Black { "TB" } else { "TW" }, seconds / 60, seconds % 60);
+        self.send(&format!("set game (;GM[Othello]PC[revello]TY[8]{}BO[{}];)", clock, format_board(board)))?;
+        self.send("go")?;
+
+        loop {
+            let line = self.receive(deadline)?;
+            let Some(answer) = line.strip_prefix("===") else {
+                // status, nodestats, ...
+                continue;
+            };
+            let ggf_move = parse_move(turn, answer.trim())?;
+            return match ggf_move.ply {
+                Some(ply) if ply.is_in(possible_plys(board)) => Ok(ply),
+                Some(ply) => Err(format!("illegal move {}", ply.to_notation())),
+                None => Err("pass while a move is possible".to_string()),
+            };
+        }
+    }
+
+    fn stop(&mut self) {
+        self.stopped = true;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl ExternalEnginePlayer {
+    // `command` is the program followed by its arguments, separated by spaces
+    pub fn new(command: &str) -> Result<Self, String> {
+        let mut engine = EngineProcess::spawn(command)?;
+        let name = engine.handshake()
+            .map_err(|e| format!("{}: {}", command, e))?
+            .unwrap_or(command.to_string());
+        Ok(ExternalEnginePlayer {
+            engine: Arc::new(Mutex::new(engine)),
+            name: name,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Player for ExternalEnginePlayer {
+    fn generate_ply(&self, board: &Board, time_left: Duration) -> Option<Ply> {
+        let mut engine = self.engine.lock().unwrap();
+        match engine.ask_move(board, time_left) {
+            Ok(ply) => Some(ply),
+            Err(e) => {
+                eprintln!("{} forfeits: {}", self.name, e);
+                engine.stop();
+                None
+            }
+        }
+    }
+}
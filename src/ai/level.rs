@@ -0,0 +1,112 @@
+// Strength levels
+//
+// Level 20 is the engine at full strength, level 1 is meant to be beatable by a beginner.
+// A level weakens the engine in four ways: it caps the search depth and the number of nodes
+// searched, it adds noise to the evaluation so the engine misjudges positions, and it
+// sometimes plays one of the next best moves instead of the best one.
+
+use std::fmt;
+
+pub const MIN_LEVEL: u8 = 1;
+pub const MAX_LEVEL: u8 = 20;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Level {
+    level: u8,
+}
+
+// What a level translates to. Can also be put together by hand to tune a level.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Strength {
+    pub max_depth: u32,
+    pub max_nodes: Option<u64>,
+    // amplitude of the evaluation noise in centi-discs
+    pub noise: i32,
+    // chance of playing one of the next best moves instead of the best one
+    pub blunder_probability: f64,
+}
+
+#[rustfmt::skip]
+const MAX_DEPTH: [u32; MAX_LEVEL as usize] = [
+    1, 1, 1, 2, 2, 2, 3, 3, 4, 4,
+    5, 5, 6, 6, 7, 8, 9, 10, 12, 60,
+];
+
+impl Level {
+    pub fn new(level: u8) -> Option<Self> {
+        if (MIN_LEVEL..=MAX_LEVEL).contains(&level) {
+            return Some(Level { level: level });
+        }
+        None
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn strength(&self) -> Strength {
+        if self.level == MAX_LEVEL {
+            return Strength::FULL;
+        }
+        // noise and blunders fall off quickly, so that the weakest levels play almost at
+        // random while the strong ones are hardly affected
+        let weakness = (MAX_LEVEL - self.level) as i32;
+        let max_weakness = (MAX_LEVEL - MIN_LEVEL) as f64;
+        Strength {
+            max_depth: MAX_DEPTH[self.level as usize - 1],
+            max_nodes: Some(1000 << self.level),
+            noise: 25 * weakness * weakness,
+            blunder_probability: 0.5 * (weakness as f64 / max_weakness).powi(2),
+        }
+    }
+}
+
+impl std::str::FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse::<u8>().ok()
+            .and_then(Level::new)
+            .ok_or(format!("level must be between {} and {}, got {}", MIN_LEVEL, MAX_LEVEL, s))
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "level {}", self.level)
+    }
+}
+
+impl Strength {
+    pub const FULL: Strength = Strength {
+        max_depth: 60,
+        max_nodes: None,
+        noise: 0,
+        blunder_probability: 0.0,
+    };
+}
+
+impl Default for Strength {
+    fn default() -> Self {
+        Strength::FULL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_get_stronger() {
+        for level in MIN_LEVEL..MAX_LEVEL {
+            let weaker = Level::new(level).unwrap().strength();
+            let stronger = Level::new(level + 1).unwrap().strength();
+            assert!(weaker.max_depth <= stronger.max_depth);
+            assert!(weaker.noise > stronger.noise);
+            assert!(weaker.blunder_probability > stronger.blunder_probability);
+        }
+        assert_eq!(Level::new(MAX_LEVEL).unwrap().strength(), Strength::FULL);
+        assert!(Level::new(0).is_none());
+        assert!("21".parse::<Level>().is_err());
+    }
+}
@@ -1,41 +1,102 @@
 use crate::board::board::{Board, Ply, possible_plys, play, Player};
 use std::cmp::{Ordering, max, min};
-use crate::ai::static_evaluation::{static_eval};
-use crate::ai::transposition_table::{TranspositionTable, TranspositionEntry, move_ordering};
+use crate::ai::evaluator::{Evaluator, evaluate_for_black};
+use crate::ai::transposition_table::{TranspositionTable, TranspositionEntry, Bound};
+use crate::ai::move_ordering::MoveOrdering;
+use crate::ai::probcut::{ProbCut, MIN_PROBCUT_DEPTH, phase};
 use std::time::{Instant, Duration};
 
 
-#[derive(Copy, Clone, PartialEq, Hash)]
+#[derive(Copy, Clone, PartialEq)]
 pub struct MinMaxResponse {
     pub eval: MinMaxEval,
     pub ply: Option<Ply>
 }
 
-#[derive(Copy, Clone, Hash)]
-pub struct MinMaxEval {
-    pub value: i32
+// The score of a position.
+//
+// Heuristic scores are estimates in centi-discs: +150 means black is expected to finish about
+// one and a half discs ahead. Once the search reaches the end of the game the result is
+// proven: the final disc differential (empty squares go to the winner) and the number of plys
+// it takes to get there. Inside the search scores are from black's point of view.
+//
+// Any win is better than any heuristic score, which is better than any loss. Among wins a
+// larger one is better, and among equally large wins a faster one is; losses are the mirror
+// image, so a lost position is dragged out as long as possible.
+#[derive(Copy, Clone, Debug)]
+pub enum MinMaxEval {
+    Heuristic(i32),
+    Proven { discs: i32, plys: u32 },
 }
 
+// every proven win sorts above every heuristic score
+const PROVEN_OFFSET: i64 = 1 << 40;
+// every extra disc outweighs any number of plys
+const DISC_WEIGHT: i64 = 1 << 16;
+
 impl MinMaxEval {
-    pub fn new(value: i32) -> Self{
-        return MinMaxEval {
-            value: value
+    pub fn heuristic(centi_discs: i32) -> Self {
+        return MinMaxEval::Heuristic(centi_discs);
+    }
+
+    pub fn proven(discs: i32) -> Self {
+        return MinMaxEval::Proven { discs: discs, plys: 0 };
+    }
+
+    // Sentinels for an empty alpha-beta window. They are worse (better) than any result a
+    // game can have, and negating one gives the other.
+    pub const MAX: MinMaxEval = MinMaxEval::Proven { discs: 65, plys: 0 };
+    pub const MIN: MinMaxEval = MinMaxEval::Proven { discs: -65, plys: 0 };
+
+    pub const ZERO: MinMaxEval = MinMaxEval::Heuristic(0);
+
+    pub fn is_proven(&self) -> bool {
+        matches!(self, MinMaxEval::Proven { .. })
+    }
+
+    pub fn centi_discs(&self) -> i32 {
+        // the score on a single scale, forgetting whether it is proven
+        match *self {
+            MinMaxEval::Heuristic(centi_discs) => centi_discs,
+            MinMaxEval::Proven { discs, .. } => discs * 100,
         }
     }
-    pub const MAX: MinMaxEval =
-        MinMaxEval {
-            value: i32::MAX,
-        };
 
-    pub const MIN: MinMaxEval =
-        MinMaxEval {
-            value: i32::MIN,
-        };
+    pub fn discs(&self) -> f32 {
+        self.centi_discs() as f32 / 100.0
+    }
 
-    pub const ZERO: MinMaxEval =
-        MinMaxEval {
-            value: 0,
-        };
+    pub fn add_ply(self) -> Self {
+        // the score seen one ply closer to the root
+        match self {
+            MinMaxEval::Heuristic(_) => self,
+            MinMaxEval::Proven { discs, plys } => MinMaxEval::Proven { discs: discs, plys: plys + 1 },
+        }
+    }
+
+    pub fn for_player(self, player: Player) -> Self {
+        // turns a score from black's point of view into one from `player`'s
+        if player == Player::White { -self } else { self }
+    }
+
+    fn key(&self) -> i64 {
+        match *self {
+            MinMaxEval::Heuristic(centi_discs) => centi_discs as i64,
+            MinMaxEval::Proven { discs, plys } => {
+                let discs = discs as i64;
+                let plys = plys as i64;
+                if discs > 0 {
+                    PROVEN_OFFSET + discs * DISC_WEIGHT - plys
+                }
+                else if discs < 0 {
+                    -PROVEN_OFFSET + discs * DISC_WEIGHT + plys
+                }
+                else {
+                    0
+                }
+            }
+        }
+    }
 }
 
 impl MinMaxResponse {
@@ -66,7 +127,7 @@ impl MinMaxResponse {
 
 impl PartialEq for MinMaxEval {
     fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
+        self.key() == other.key()
     }
 }
 
@@ -80,67 +141,184 @@ impl PartialOrd for MinMaxEval {
 
 impl Ord for MinMaxEval {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.value.cmp(&other.value)
+        self.key().cmp(&other.key())
+    }
+}
+
+impl std::ops::Neg for MinMaxEval {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        match self {
+            MinMaxEval::Heuristic(centi_discs) => MinMaxEval::Heuristic(centi_discs.saturating_neg()),
+            MinMaxEval::Proven { discs, plys } => MinMaxEval::Proven { discs: -discs, plys: plys },
+        }
+    }
+}
+
+impl std::fmt::Display for MinMaxEval {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            MinMaxEval::Heuristic(centi_discs) => write!(f, "{:+.2}", centi_discs as f32 / 100.0),
+            MinMaxEval::Proven { discs, plys } => {
+                let result = if discs > 0 { "win" } else if discs < 0 { "loss" } else { "draw" };
+                write!(f, "{:+} ({} in {})", discs, result, plys)
+            }
+        }
+    }
+}
+
+pub struct SearchContext<'a, E: Evaluator> {
+    pub transposition_table: &'a mut TranspositionTable,
+    pub evaluator: &'a mut E,
+    pub probcut: &'a ProbCut,
+    pub ordering: MoveOrdering,
+    pub stats: SearchStats,
+    pub start_time: Instant,
+    pub thinking_time: Duration,
+    pub max_nodes: Option<u64>,
+    // number of plys between the root and the node being searched
+    pub height: u32,
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SearchStats {
+    pub nodes: u64,
+    // nodes where a move caused a cutoff
+    pub cutoffs: u64,
+    // ... and where that move was the first one searched
+    pub first_move_cutoffs: u64,
+}
+
+impl SearchStats {
+    pub fn first_move_cutoff_percentage(&self) -> f64 {
+        // how good the move ordering is: ideally every cutoff comes from the first move
+        if self.cutoffs == 0 {
+            return 0.0;
+        }
+        100.0 * self.first_move_cutoffs as f64 / self.cutoffs as f64
+    }
+
+    fn record_cutoff(&mut self, move_index: usize) {
+        self.cutoffs += 1;
+        if move_index == 0 {
+            self.first_move_cutoffs += 1;
+        }
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} nodes, {:.1}% first move cutoffs", self.nodes, self.first_move_cutoff_percentage())
+    }
+}
+
+impl<'a, E: Evaluator> SearchContext<'a, E> {
+    pub fn new(
+        transposition_table: &'a mut TranspositionTable,
+        evaluator: &'a mut E,
+        probcut: &'a ProbCut,
+        start_time: Instant,
+        thinking_time: Duration,
+        ) -> Self {
+        SearchContext {
+            transposition_table: transposition_table,
+            evaluator: evaluator,
+            probcut: probcut,
+            ordering: MoveOrdering::new(),
+            stats: SearchStats::default(),
+            start_time: start_time,
+            thinking_time: thinking_time,
+            max_nodes: None,
+            height: 0,
+        }
     }
 }
 
-pub fn min_max(board: Board,
+pub fn min_max<E: Evaluator>(board: Board,
     depth: u32,
     alpha: &MinMaxEval,
     beta: &MinMaxEval,
-    transposition_table:
-    &mut TranspositionTable,
-    start_time: Instant,
-    thinking_time: Duration
-    ) 
+    ctx: &mut SearchContext<E>,
+    )
 -> Option<MinMaxResponse>
 {
     // returns a Min Max response that gives the best move accorning to the bot
     // returns None if search is quit
 
     // quit search
-    if Instant::now() - start_time >= thinking_time {
+    if Instant::now() - ctx.start_time >= ctx.thinking_time {
         return None;
     }
+    if ctx.max_nodes.is_some_and(|max_nodes| ctx.stats.nodes >= max_nodes) {
+        return None;
+    }
+    ctx.stats.nodes += 1;
 
-    if let Some(lookup_response) = transposition_table.get(&board) {
-        if lookup_response.get_depth() >= depth {
-            return Some(lookup_response.get_minmax_response());
+    if let Some(lookup_response) = ctx.transposition_table.get(&board) {
+        // a bound is only good enough when it falls outside the window
+        let response = lookup_response.get_minmax_response();
+        let usable = match lookup_response.get_bound() {
+            Bound::Exact => true,
+            Bound::Lower => response.eval > *beta,
+            Bound::Upper => response.eval < *alpha,
+        };
+        if lookup_response.get_depth() >= depth && usable {
+            return Some(response);
         }
     }
 
     if depth == 0 {
-        let response = static_eval(&board);
+        let response = MinMaxResponse::new_empty_ply(evaluate_for_black(ctx.evaluator, &board));
         let entry = TranspositionEntry::new(response, depth);
-        transposition_table.insert(board, entry);
+        ctx.transposition_table.insert(board, entry);
         return Some(response);
     }
     let plys = possible_plys(&board);
 
     if plys.is_zero() {
-        let response = static_eval(&board);
+        let response = MinMaxResponse::new_empty_ply(evaluate_for_black(ctx.evaluator, &board));
         let entry = TranspositionEntry::new(response, depth);
-        transposition_table.insert(board, entry);
+        ctx.transposition_table.insert(board, entry);
         return Some(response);
     }
 
-    let mut alpha = alpha.clone();
-    let mut beta = beta.clone();
+    if ctx.height > 0 && depth >= MIN_PROBCUT_DEPTH && !ctx.probcut.selectivity().is_none() {
+        match probcut(&board, depth, alpha, beta, ctx) {
+            None => return None,
+            Some(Some(response)) => return Some(response),
+            Some(None) => {},
+        }
+    }
+
+    let window = (*alpha, *beta);
+    let mut alpha = *alpha;
+    let mut beta = *beta;
 
     // Maximizing player
     if board.turn == Some(Player::Black) {
         let mut best_move = MinMaxResponse::MIN;
-        for ply in move_ordering(&board, &transposition_table, depth) {
-            let new_board = play(&board, ply.clone());
-            if let Some(min_max_val) = min_max(new_board, depth - 1, &alpha, &beta, transposition_table, start_time, thinking_time) {
-                if min_max_val.eval >= best_move.eval {
-                    best_move.eval = min_max_val.eval;
+        for (move_index, ply) in ctx.ordering.order(&board, ctx.transposition_table, depth, ctx.height).into_iter().enumerate() {
+            let new_board = play(&board, ply);
+            ctx.evaluator.push(&board, ply);
+            ctx.height += 1;
+            let response = min_max(new_board, depth - 1, &alpha, &beta, ctx);
+            ctx.height -= 1;
+            ctx.evaluator.pop();
+            if let Some(min_max_val) = response {
+                let eval = min_max_val.eval.add_ply();
+                // a later move scoring the same may only be a bound, the first one is kept. The sentinel
+                // starting value is worse than any score, so the first move is always taken
+                if eval > best_move.eval {
+                    best_move.eval = eval;
                     best_move.ply = Some(ply);
                 }
 
                 alpha = max(best_move.eval, alpha);
 
                 if beta < alpha {
+                    ctx.stats.record_cutoff(move_index);
+                    ctx.ordering.record_cutoff(&board, ply, depth, ctx.height);
                     break;
                 }
             }
@@ -149,23 +327,31 @@ pub fn min_max(board: Board,
             }
 
         }
-        transposition_table.insert(board, TranspositionEntry::new(best_move, depth));
+        ctx.transposition_table.insert(board, TranspositionEntry::with_bound(best_move, depth, window_bound(&best_move.eval, &window)));
         return Some(best_move);
     }
     // Minimizing player
     else {
         let mut best_move = MinMaxResponse::MAX;
-        for ply in move_ordering(&board, &transposition_table, depth) {
-            let new_board = play(&board, ply.clone());
-            if let Some(min_max_val) = min_max(new_board, depth - 1, &alpha, &beta, transposition_table, start_time, thinking_time) {
-                if min_max_val.eval <= best_move.eval {
-                    best_move.eval = min_max_val.eval;
+        for (move_index, ply) in ctx.ordering.order(&board, ctx.transposition_table, depth, ctx.height).into_iter().enumerate() {
+            let new_board = play(&board, ply);
+            ctx.evaluator.push(&board, ply);
+            ctx.height += 1;
+            let response = min_max(new_board, depth - 1, &alpha, &beta, ctx);
+            ctx.height -= 1;
+            ctx.evaluator.pop();
+            if let Some(min_max_val) = response {
+                let eval = min_max_val.eval.add_ply();
+                if eval < best_move.eval {
+                    best_move.eval = eval;
                     best_move.ply = Some(ply);
                 }
 
                 beta = min(beta, best_move.eval);
 
                 if beta < alpha {
+                    ctx.stats.record_cutoff(move_index);
+                    ctx.ordering.record_cutoff(&board, ply, depth, ctx.height);
                     break;
                 }
             }
@@ -174,8 +360,139 @@ pub fn min_max(board: Board,
             }
 
         }
-        transposition_table.insert(board, TranspositionEntry::new(best_move, depth));
+        ctx.transposition_table.insert(board, TranspositionEntry::with_bound(best_move, depth, window_bound(&best_move.eval, &window)));
         return Some(best_move);
     }
 }
 
+fn window_bound(eval: &MinMaxEval, (alpha, beta): &(MinMaxEval, MinMaxEval)) -> Bound {
+    // only a score strictly inside the window the node was searched with is exact
+    if eval <= alpha {
+        Bound::Upper
+    } else if eval >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    }
+}
+
+fn probcut<E: Evaluator>(
+    board: &Board,
+    depth: u32,
+    alpha: &MinMaxEval,
+    beta: &MinMaxEval,
+    ctx: &mut SearchContext<E>,
+    )
+-> Option<Option<MinMaxResponse>>
+{
+    // Tries to predict a cutoff of a `depth` deep search with a shallow search.
+    // returns None if search is quit, Some(None) if no cut could be made
+
+    let params = match ctx.probcut.get(phase(board), depth) {
+        Some(params) => *params,
+        None => return Some(None),
+    };
+    let (low, high) = ctx.probcut.bounds(&params, alpha, beta);
+    if low.is_none() && high.is_none() {
+        return Some(None);
+    }
+
+    // null window searches around the bounds are enough to tell which side they fall on.
+    // A cut returns the predicted deep value, which lies strictly outside the window
+    if let Some(high) = high {
+        let shallow = min_max(board.clone(), params.shallow_depth, &MinMaxEval::heuristic(high - 1), &MinMaxEval::heuristic(high), ctx)?;
+        if shallow.eval >= MinMaxEval::heuristic(high) {
+            return Some(Some(MinMaxResponse::new_empty_ply(params.predict(shallow.eval))));
+        }
+    }
+    if let Some(low) = low {
+        let shallow = min_max(board.clone(), params.shallow_depth, &MinMaxEval::heuristic(low), &MinMaxEval::heuristic(low + 1), ctx)?;
+        if shallow.eval <= MinMaxEval::heuristic(low) {
+            return Some(Some(MinMaxResponse::new_empty_ply(params.predict(shallow.eval))));
+        }
+    }
+    Some(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::evaluator::DiscCountEvaluator;
+    use crate::ai::static_evaluation::final_eval;
+    use crate::board::board::START_BOARD;
+
+    #[test]
+    fn test_eval_order() {
+        let big_slow_win = MinMaxEval::Proven { discs: 10, plys: 9 };
+        let big_fast_win = MinMaxEval::Proven { discs: 10, plys: 3 };
+        let small_win = MinMaxEval::Proven { discs: 2, plys: 1 };
+        let fast_loss = MinMaxEval::Proven { discs: -10, plys: 1 };
+        let slow_loss = MinMaxEval::Proven { discs: -10, plys: 7 };
+
+        assert!(big_fast_win > big_slow_win);
+        assert!(big_slow_win > small_win);
+        assert!(small_win > MinMaxEval::heuristic(6000));
+        assert!(MinMaxEval::heuristic(-6000) > slow_loss);
+        assert!(slow_loss > fast_loss);
+        assert!(MinMaxEval::MAX > big_fast_win);
+        assert!(MinMaxEval::MIN < fast_loss);
+        assert_eq!(MinMaxEval::proven(0), MinMaxEval::ZERO);
+    }
+
+    #[test]
+    fn test_eval_negation() {
+        assert_eq!(-MinMaxEval::MAX, MinMaxEval::MIN);
+        assert_eq!(-MinMaxEval::MIN, MinMaxEval::MAX);
+        assert_eq!(-MinMaxEval::heuristic(i32::MIN), MinMaxEval::heuristic(i32::MAX));
+        let win = MinMaxEval::Proven { discs: 4, plys: 3 };
+        assert_eq!(-(-win), win);
+        assert!(-MinMaxEval::Proven { discs: 4, plys: 5 } > -win);
+    }
+
+    #[test]
+    fn test_eval_display() {
+        assert_eq!(MinMaxEval::heuristic(125).to_string(), "+1.25");
+        assert_eq!(MinMaxEval::heuristic(-50).to_string(), "-0.50");
+        assert_eq!(MinMaxEval::Proven { discs: 6, plys: 4 }.to_string(), "+6 (win in 4)");
+        assert_eq!(MinMaxEval::Proven { discs: -2, plys: 1 }.add_ply().to_string(), "-2 (loss in 2)");
+    }
+
+    fn brute_force(board: &Board) -> i32 {
+        // plain minimax, without any of the search's shortcuts
+        match board.turn {
+            None => final_eval(board).eval.discs() as i32,
+            Some(turn) => {
+                let scores = possible_plys(board).into_iter().map(|ply| brute_force(&play(board, ply)));
+                if turn == Player::Black { scores.max().unwrap() } else { scores.min().unwrap() }
+            }
+        }
+    }
+
+    #[test]
+    fn test_solve() {
+        // bounds from cut off searches used to be stored in the transposition table as exact
+        // scores, and a move only as good as a bound could be picked over the best one
+        use rand::{Rng, SeedableRng};
+        use rand::rngs::StdRng;
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let mut board = START_BOARD;
+            while board.turn.is_some() && board.count_pieces() < 56 {
+                let plys = possible_plys(&board).to_vec_ply();
+                board = play(&board, plys[rng.random_range(0..plys.len())]);
+            }
+            let mut transposition_table = TranspositionTable::new();
+            let mut evaluator = DiscCountEvaluator;
+            let probcut = ProbCut::none();
+            let mut ctx = SearchContext::new(&mut transposition_table, &mut evaluator, &probcut, Instant::now(), Duration::MAX);
+            let depth = 64 - board.count_pieces() as u32;
+            let response = min_max(board.clone(), depth, &MinMaxEval::MIN, &MinMaxEval::MAX, &mut ctx).unwrap();
+            let discs = response.eval.discs() as i32;
+            assert_eq!(discs, brute_force(&board));
+            if let Some(ply) = response.ply {
+                assert_eq!(brute_force(&play(&board, ply)), discs);
+            }
+        }
+    }
+}
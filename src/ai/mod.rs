@@ -1,4 +1,12 @@
 pub mod player;
+pub mod external_engine;
+pub mod evaluator;
 pub mod minmax;
+pub mod probcut;
+pub mod level;
+pub mod calibration;
+pub mod tournament;
 pub mod static_evaluation;
 pub mod transposition_table;
+pub mod move_ordering;
+pub mod xot;
@@ -0,0 +1,155 @@
+// Move ordering
+//
+// Alpha-beta cuts the most when the best move is searched first. Moves are sorted by, in
+// order of importance:
+//  - the best move stored in the transposition table by an earlier (shallower) search
+//  - killer moves: moves that caused a cutoff at the same ply elsewhere in the tree
+//  - fastest first: moves that leave the opponent the fewest replies
+//  - the history table: how often (and how deep) a square caused cutoffs for the side to move
+//  - a static square priority table: corners first, squares next to corners last
+
+use crate::board::board::{Board, Ply, Player, possible_plys, play};
+use crate::ai::transposition_table::TranspositionTable;
+use std::cmp::Reverse;
+
+const TT_MOVE_SCORE: i64 = 1 << 40;
+const KILLER_SCORE: i64 = 1 << 36;
+const MOBILITY_WEIGHT: i64 = 1 << 20;
+const HISTORY_MAX: u32 = 1 << 18;
+const PRIORITY_WEIGHT: i64 = 1 << 12;
+
+// fastest first needs a move generation per move, only worth it away from the leaves
+const FASTEST_FIRST_DEPTH: u32 = 2;
+
+#[rustfmt::skip]
+const SQUARE_PRIORITY: [i64; 64] = [
+    9, 2, 8, 6, 6, 8, 2, 9,
+    2, 1, 3, 4, 4, 3, 1, 2,
+    8, 3, 7, 5, 5, 7, 3, 8,
+    6, 4, 5, 0, 0, 5, 4, 6,
+    6, 4, 5, 0, 0, 5, 4, 6,
+    8, 3, 7, 5, 5, 7, 3, 8,
+    2, 1, 3, 4, 4, 3, 1, 2,
+    9, 2, 8, 6, 6, 8, 2, 9,
+];
+
+#[derive(Clone)]
+pub struct MoveOrdering {
+    // two killer moves for every height in the tree
+    killers: Vec<[Option<Ply>; 2]>,
+    // history[side][square]
+    history: [[u32; 64]; 2],
+}
+
+fn side_index(turn: Option<Player>) -> usize {
+    if turn == Some(Player::White) { 1 } else { 0 }
+}
+
+fn square(ply: Ply) -> usize {
+    u64::from(ply).trailing_zeros() as usize
+}
+
+fn opponent_mobility(board: &Board, ply: Ply) -> i64 {
+    let new_board = play(board, ply);
+    if new_board.turn == board.turn {
+        // the opponent has to pass
+        return -1;
+    }
+    u64::from(possible_plys(&new_board)).count_ones() as i64
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        MoveOrdering {
+            killers: Vec::new(),
+            history: [[0; 64]; 2],
+        }
+    }
+
+    pub fn order(&self, board: &Board, transposition_table: &TranspositionTable, depth: u32, height: u32) -> Vec<Ply> {
+        let plys = possible_plys(board);
+        if plys.is_zero() {
+            return vec![];
+        }
+        let tt_move = transposition_table.get(board).and_then(|entry| entry.get_minmax_response().ply);
+        let killers = self.killers.get(height as usize).copied().unwrap_or([None; 2]);
+        let history = &self.history[side_index(board.turn)];
+
+        let mut scored_moves: Vec<(Ply, i64)> = plys.into_iter().map(|ply| {
+            if Some(ply) == tt_move {
+                return (ply, TT_MOVE_SCORE);
+            }
+            if Some(ply) == killers[0] {
+                return (ply, KILLER_SCORE + 1);
+            }
+            if Some(ply) == killers[1] {
+                return (ply, KILLER_SCORE);
+            }
+            let square = square(ply);
+            let mut score = history[square] as i64 + SQUARE_PRIORITY[square] * PRIORITY_WEIGHT;
+            if depth >= FASTEST_FIRST_DEPTH {
+                score -= opponent_mobility(board, ply) * MOBILITY_WEIGHT;
+            }
+            (ply, score)
+        }).collect();
+
+        // stable sort keeps bit order among equal moves
+        scored_moves.sort_by_key(|a| Reverse(a.1));
+        scored_moves.into_iter().map(|(ply, _)| ply).collect()
+    }
+
+    pub fn record_cutoff(&mut self, board: &Board, ply: Ply, depth: u32, height: u32) {
+        let height = height as usize;
+        if self.killers.len() <= height {
+            self.killers.resize(height + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[height];
+        if killers[0] != Some(ply) {
+            killers[1] = killers[0];
+            killers[0] = Some(ply);
+        }
+
+        let history = &mut self.history[side_index(board.turn)];
+        history[square(ply)] += depth * depth;
+        if history[square(ply)] > HISTORY_MAX {
+            // age the table so that recent cutoffs count more
+            for value in history.iter_mut() {
+                *value /= 2;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::START_BOARD;
+
+    #[test]
+    fn test_order_keeps_all_moves() {
+        let ordering = MoveOrdering::new();
+        let transposition_table = TranspositionTable::new();
+        for depth in 1..3 {
+            let mut ordered = ordering.order(&START_BOARD, &transposition_table, depth, 0);
+            let mut plys = possible_plys(&START_BOARD).to_vec_ply();
+            ordered.sort_by_key(|ply| u64::from(*ply));
+            plys.sort_by_key(|ply| u64::from(*ply));
+            assert!(ordered == plys);
+        }
+    }
+
+    #[test]
+    fn test_killer_is_searched_first() {
+        let mut ordering = MoveOrdering::new();
+        let transposition_table = TranspositionTable::new();
+        let last = *possible_plys(&START_BOARD).to_vec_ply().last().unwrap();
+        ordering.record_cutoff(&START_BOARD, last, 3, 2);
+        assert!(ordering.order(&START_BOARD, &transposition_table, 3, 2)[0] == last);
+    }
+}
@@ -1,104 +1,326 @@
-use crate::board::board::{Board, Ply, possible_plys, Player as BoardPlayer};
+use crate::board::board::{Board, Ply, possible_plys, play, Player as BoardPlayer};
 use std::time::Duration;
-use crate::ai::minmax::{min_max, MinMaxResponse, MinMaxEval};
+use crate::ai::minmax::{min_max, MinMaxResponse, MinMaxEval, SearchContext, SearchStats};
 use crate::ai::transposition_table::{TranspositionTable};
-use crate::graphics::graphics::{draw_playable, detect_ply};
+use crate::ai::evaluator::{Evaluator, AnyEvaluator, NoisyEvaluator};
+use crate::ai::probcut::ProbCut;
+use crate::ai::level::{Level, Strength};
+use crate::ai::external_engine::ExternalEnginePlayer;
+use rand::Rng;
+use rand::seq::IndexedRandom;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 
 pub trait Player {
     fn update(&mut self, _board: &Board) {
 
     }
-    fn generate_ply(&self, board: &Board, time_left: Duration) -> Ply;
+    // None forfeits the game, for players that can fail (an external engine that crashed,
+    // ran out of time or answered an illegal move)
+    fn generate_ply(&self, board: &Board, time_left: Duration) -> Option<Ply>;
+
+    // Same as `generate_ply`, for a player thinking on another thread that may be told to stop
+    // (its clock ran out, the game was given up). Players that can wait forever give up with
+    // None once `stop` is set; the others finish their move, which is then thrown away.
+    fn generate_ply_or_stop(&self, board: &Board, time_left: Duration, _stop: &AtomicBool) -> Option<Ply> {
+        self.generate_ply(board, time_left)
+    }
+
+    // Players that wait for the user interface to pick their move (a click, a typed move)
+    // return where to send it
+    fn move_input(&self) -> Option<&MoveInput> {
+        None
+    }
 }
 
-#[derive(Clone)]
-pub struct MinMaxPlayer {
+// A move picked in the user interface, passed on to a `HumanPlayer` thinking on another thread
+#[derive(Clone, Default)]
+pub struct MoveInput {
+    selected_ply: Arc<Mutex<Option<Ply>>>,
+}
 
+#[derive(Clone)]
+pub struct MinMaxPlayer<E: Evaluator = AnyEvaluator> {
+    evaluator: E,
+    probcut: ProbCut,
+    strength: Strength,
+    // print search information after every move
+    verbose: bool,
 }
 
+// Plays a random legal move
+#[derive(Clone, Default)]
+pub struct RandomPlayer;
+
+// Plays the move that flips the most discs
+#[derive(Clone, Default)]
+pub struct GreedyPlayer;
+
 #[derive(Clone)]
+pub enum AnyPlayer {
+    Human(HumanPlayer),
+    MinMax(MinMaxPlayer),
+    Random(RandomPlayer),
+    Greedy(GreedyPlayer),
+    External(ExternalEnginePlayer),
+}
+
+// Plays the moves picked in the user interface
+#[derive(Clone, Default)]
 pub struct HumanPlayer {
-    selected_ply: Arc<Mutex<Option<Ply>>>,
-    player: BoardPlayer
+    input: MoveInput,
 }
 
 impl MinMaxPlayer {
     pub fn new() -> Self {
-        return MinMaxPlayer{};
+        return MinMaxPlayer::with_evaluator(AnyEvaluator::default());
+    }
+}
+
+impl Default for MinMaxPlayer {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-impl Player for MinMaxPlayer {
-    fn generate_ply(&self, board: &Board, time_left: Duration) -> Ply{
+impl<E: Evaluator> MinMaxPlayer<E> {
+    pub fn with_evaluator(evaluator: E) -> Self {
+        return MinMaxPlayer {
+            evaluator: evaluator,
+            probcut: ProbCut::none(),
+            strength: Strength::FULL,
+            verbose: true,
+        };
+    }
+
+    pub fn quiet(mut self) -> Self {
+        self.verbose = false;
+        self
+    }
+
+    pub fn with_probcut(mut self, probcut: ProbCut) -> Self {
+        self.probcut = probcut;
+        self
+    }
+
+    pub fn with_strength(mut self, strength: Strength) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    pub fn with_level(self, level: Level) -> Self {
+        self.with_strength(level.strength())
+    }
+
+    pub fn strength(&self) -> Strength {
+        self.strength
+    }
+}
+
+fn score_root_moves<E: Evaluator>(board: &Board, evaluator: &mut E, depth: u32) -> Vec<(Ply, MinMaxEval)> {
+    // all legal moves with their score for the side to move, best first, according to a
+    // search of `depth`
+    let mut transposition_table = TranspositionTable::new();
+    let probcut = ProbCut::none();
+    let mut ctx = SearchContext::new(&mut transposition_table, evaluator, &probcut, std::time::Instant::now(), Duration::MAX);
+    let mut scored: Vec<(Ply, MinMaxEval)> = possible_plys(board).into_iter().map(|ply| {
+        let eval = min_max(play(board, ply), depth.saturating_sub(1), &MinMaxEval::MIN, &MinMaxEval::MAX, &mut ctx)
+            .expect("search without a time limit returned None")
+            .eval
+            .add_ply();
+        (ply, eval.for_player(board.turn.expect("no move to score")))
+    }).collect();
+    scored.sort_by_key(|a| std::cmp::Reverse(a.1));
+    scored
+}
+
+// The outcome of a search for the move to play
+#[derive(Clone, Copy)]
+pub struct SearchInfo {
+    pub ply: Ply,
+    // from black's point of view, None if not even a depth 1 search finished in time
+    pub eval: Option<MinMaxEval>,
+    // deepest search that finished
+    pub depth: u32,
+    pub stats: SearchStats,
+    pub time: Duration,
+}
+
+impl<E: Evaluator + Clone> MinMaxPlayer<E> {
+    pub fn search(&self, board: &Board, thinking_time: Duration) -> SearchInfo {
+        // Iterative deepening until the time, the depth or the node limit runs out
 
         let alpha = MinMaxEval::MIN;
         let beta = MinMaxEval::MAX;
         let mut transposition_table = TranspositionTable::new();
-        let mut response = MinMaxResponse::ZERO;
+        let mut evaluator = NoisyEvaluator::new(self.evaluator.clone(), self.strength.noise, rand::random());
+        let mut response: Option<MinMaxResponse> = None;
         use std::time::Instant;
 
         let start = Instant::now(); // start timer
+        let mut depth = 0;
+        let mut ctx = SearchContext::new(&mut transposition_table, &mut evaluator, &self.probcut, start, thinking_time);
+        ctx.max_nodes = self.strength.max_nodes;
+
+        while depth < self.strength.max_depth {
+            match min_max(board.clone(), depth + 1, &alpha, &beta, &mut ctx) {
+                Some(res) => response = Some(res),
+                None => break,
+            }
+            depth += 1;
+        }
+        let stats = ctx.stats;
+
+        let mut rng = rand::rng();
+        let ply = if self.strength.blunder_probability > 0.0 && rng.random_bool(self.strength.blunder_probability.min(1.0)) {
+            // play one of the next best moves instead
+            let ranked = score_root_moves(board, &mut evaluator, depth.clamp(1, 3));
+            ranked.get(1..ranked.len().min(4)).and_then(|worse| worse.choose(&mut rng)).map(|(ply, _)| *ply)
+        } else {
+            None
+        };
+        let ply = ply
+            .or(response.and_then(|response| response.ply))
+            // not even a depth 1 search finished in time
+            .unwrap_or_else(|| score_root_moves(board, &mut evaluator, 0)[0].0);
+
+        SearchInfo {
+            ply: ply,
+            eval: response.map(|response| response.eval),
+            depth: depth,
+            stats: stats,
+            time: start.elapsed(),
+        }
+    }
+
+    pub fn analyse_moves(&self, board: &Board, depth: u32) -> Vec<(Ply, MinMaxEval)> {
+        // every legal move with its score for the side to move, best first
+        let mut evaluator = self.evaluator.clone();
+        score_root_moves(board, &mut evaluator, depth)
+    }
+}
+
+impl<E: Evaluator + Clone> Player for MinMaxPlayer<E> {
+    fn generate_ply(&self, board: &Board, time_left: Duration) -> Option<Ply> {
+
         let factor = 2.0 / (64.0 - board.count_pieces() as f32);
         let allowed_thinking_time = Duration::from_secs_f32(time_left.as_secs_f32() * factor);
-        let mut depth = 1;
+        let info = self.search(board, allowed_thinking_time);
 
-        loop {
-            if let Some(res) = min_max(board.clone(), depth as u32, &alpha, &beta, &mut transposition_table, start, allowed_thinking_time) {
-                response = res;
+        if self.verbose {
+            if let Some(eval) = info.eval {
+                println!("Eval: {}", eval);
             }
-            else {
-                break;
+            println!("Depth: {}", info.depth);
+            println!("Search: {}", info.stats);
+            if !self.probcut.selectivity().is_none() {
+                println!("Selectivity: {}", self.probcut.selectivity());
             }
+            println!("Time elapsed: {:?}", info.time);
+        }
 
-            if depth >= 60 {
-                break;
-            }
+        Some(info.ply)
 
-            depth += 1;
+    }
+}
+
+impl Player for RandomPlayer {
+    fn generate_ply(&self, board: &Board, _time_left: Duration) -> Option<Ply> {
+        possible_plys(board).to_vec_ply().choose(&mut rand::rng()).copied()
+    }
+}
+
+impl Player for GreedyPlayer {
+    fn generate_ply(&self, board: &Board, _time_left: Duration) -> Option<Ply> {
+        let turn = board.turn?;
+        possible_plys(board).into_iter()
+            .max_by_key(|ply| {
+                let new_board = play(board, *ply);
+                if turn == BoardPlayer::Black { new_board.count_black() } else { new_board.count_white() }
+            })
+    }
+}
+
+impl Player for AnyPlayer {
+    fn update(&mut self, board: &Board) {
+        match self {
+            AnyPlayer::Human(player) => player.update(board),
+            AnyPlayer::MinMax(player) => player.update(board),
+            AnyPlayer::Random(player) => player.update(board),
+            AnyPlayer::Greedy(player) => player.update(board),
+            AnyPlayer::External(player) => player.update(board),
         }
+    }
 
-        let duration = start.elapsed(); // time elapsed
-        println!("Eval: {}", response.eval.value);
-        println!("Depth: {}", depth);
-        println!("Time elapsed: {:?}", duration);
+    fn generate_ply(&self, board: &Board, time_left: Duration) -> Option<Ply> {
+        match self {
+            AnyPlayer::Human(player) => player.generate_ply(board, time_left),
+            AnyPlayer::MinMax(player) => player.generate_ply(board, time_left),
+            AnyPlayer::Random(player) => player.generate_ply(board, time_left),
+            AnyPlayer::Greedy(player) => player.generate_ply(board, time_left),
+            AnyPlayer::External(player) => player.generate_ply(board, time_left),
+        }
+    }
 
-        let ply = response.ply.expect("invalid move");
+    fn generate_ply_or_stop(&self, board: &Board, time_left: Duration, stop: &AtomicBool) -> Option<Ply> {
+        match self {
+            AnyPlayer::Human(player) => player.generate_ply_or_stop(board, time_left, stop),
+            AnyPlayer::MinMax(player) => player.generate_ply_or_stop(board, time_left, stop),
+            AnyPlayer::Random(player) => player.generate_ply_or_stop(board, time_left, stop),
+            AnyPlayer::Greedy(player) => player.generate_ply_or_stop(board, time_left, stop),
+            AnyPlayer::External(player) => player.generate_ply_or_stop(board, time_left, stop),
+        }
+    }
 
-        ply
+    fn move_input(&self) -> Option<&MoveInput> {
+        match self {
+            AnyPlayer::Human(player) => player.move_input(),
+            AnyPlayer::MinMax(player) => player.move_input(),
+            AnyPlayer::Random(player) => player.move_input(),
+            AnyPlayer::Greedy(player) => player.move_input(),
+            AnyPlayer::External(player) => player.move_input(),
+        }
+    }
+}
 
+impl MoveInput {
+    pub fn select(&self, ply: Ply) {
+        *self.selected_ply.lock().unwrap() = Some(ply);
+    }
+
+    fn take(&self) -> Option<Ply> {
+        self.selected_ply.lock().unwrap().take()
     }
 }
 
 impl HumanPlayer {
-    pub fn new(player: BoardPlayer) -> Self {
-        HumanPlayer {
-            selected_ply: Arc::new(Mutex::new(None)),
-            player: player,
-        }
+    pub fn new() -> Self {
+        HumanPlayer::default()
     }
 }
 
 impl Player for HumanPlayer {
-    fn update(&mut self, board: &Board) {
-        if let Some(turn) = board.turn {
-            if turn == self.player {
-                draw_playable(&board);
-                let mut sel = self.selected_ply.lock().unwrap();
-                *sel = detect_ply();
-            }
-        }
+    fn generate_ply(&self, board: &Board, time_left: Duration) -> Option<Ply> {
+        // waits for a move for as long as it takes
+        self.generate_ply_or_stop(board, time_left, &AtomicBool::new(false))
     }
 
-    fn generate_ply(&self, board: &Board, _time_left: Duration) -> Ply {
+    fn generate_ply_or_stop(&self, board: &Board, _time_left: Duration, stop: &AtomicBool) -> Option<Ply> {
         loop {
-            if let Some(ply) = *self.selected_ply.lock().unwrap() {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Some(ply) = self.input.take() {
                 if ply.is_in(possible_plys(board)) {
-                    return ply;
+                    return Some(ply);
                 }
             }
             thread::sleep(Duration::from_millis(16)); // ~60 checks/sec
         }
     }
+
+    fn move_input(&self) -> Option<&MoveInput> {
+        Some(&self.input)
+    }
 }
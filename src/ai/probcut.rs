@@ -0,0 +1,329 @@
+// Multi-ProbCut
+//
+// A shallow search is a good predictor of a deep search: v_deep ~ a * v_shallow + b with
+// normally distributed error sigma. Before searching a node to depth d, we run a search to a
+// shallower depth d' and, if the prediction lies outside the alpha-beta window by more than
+// t * sigma, we assume the deep search would cut as well and return straight away.
+//
+// a, b and sigma depend on the depth pair and on the game phase, so they are fitted per
+// (phase, depth) from a set of positions (see `fit_probcut`). t is the selectivity: the
+// smaller it is the more nodes are cut, the deeper the search gets and the more often it is
+// wrong.
+
+use crate::board::board::{Board, possible_plys, play};
+use crate::ai::minmax::{min_max, MinMaxEval, SearchContext};
+use crate::ai::evaluator::Evaluator;
+use crate::ai::transposition_table::TranspositionTable;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// ProbCut is only tried at nodes searched at least this deep
+pub const MIN_PROBCUT_DEPTH: u32 = 3;
+pub const N_PHASES: usize = 4;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ProbCutParams {
+    pub phase: usize,
+    pub depth: u32,
+    pub shallow_depth: u32,
+    pub a: f32,
+    pub b: f32,
+    pub sigma: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Selectivity {
+    level: u8,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ProbCut {
+    params: Vec<ProbCutParams>,
+    selectivity: Selectivity,
+}
+
+impl Selectivity {
+    // level 0 is a full width search, level 5 cuts the most
+    pub const NONE: Selectivity = Selectivity { level: 0 };
+    pub const MAX_LEVEL: u8 = 5;
+
+    const T: [f32; 6] = [f32::INFINITY, 3.3, 2.6, 2.0, 1.5, 1.1];
+    const CONFIDENCE: [u32; 6] = [100, 99, 98, 95, 87, 73];
+
+    pub fn new(level: u8) -> Option<Self> {
+        if level <= Self::MAX_LEVEL {
+            return Some(Selectivity { level: level });
+        }
+        None
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn t(&self) -> f32 {
+        Self::T[self.level as usize]
+    }
+
+    // how sure we are that a cut is correct, in percent
+    pub fn confidence(&self) -> u32 {
+        Self::CONFIDENCE[self.level as usize]
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.level == 0
+    }
+}
+
+impl fmt::Display for Selectivity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}%", self.confidence())
+    }
+}
+
+pub fn phase(board: &Board) -> usize {
+    // 0: opening, ..., N_PHASES - 1: endgame
+    let empties = 64 - board.count_pieces() as usize;
+    let phase = (60 - empties.min(60)) * N_PHASES / 61;
+    phase.min(N_PHASES - 1)
+}
+
+pub fn shallow_depth(depth: u32) -> u32 {
+    // the depth of the predicting search for a search of depth `depth`
+    depth.div_ceil(2).max(1).min(depth - 1)
+}
+
+impl ProbCutParams {
+    // the deep search value predicted from a shallow one
+    pub fn predict(&self, shallow: MinMaxEval) -> MinMaxEval {
+        if shallow.is_proven() {
+            return shallow;
+        }
+        MinMaxEval::heuristic((self.a * shallow.centi_discs() as f32 + self.b).round() as i32)
+    }
+}
+
+impl ProbCut {
+    pub fn new(params: Vec<ProbCutParams>, selectivity: Selectivity) -> Self {
+        ProbCut {
+            params: params,
+            selectivity: selectivity,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::new(Vec::new(), Selectivity::NONE)
+    }
+
+    pub fn with_selectivity(selectivity: Selectivity) -> Self {
+        Self::new(default_params(), selectivity)
+    }
+
+    pub fn selectivity(&self) -> Selectivity {
+        self.selectivity
+    }
+
+    pub fn params(&self) -> &[ProbCutParams] {
+        &self.params
+    }
+
+    pub fn get(&self, phase: usize, depth: u32) -> Option<&ProbCutParams> {
+        // Only depths that were fitted: a regression says nothing about how far apart searches
+        // of other depths are, so deeper searches get no cuts
+        self.params.iter().find(|p| p.phase == phase && p.depth == depth)
+    }
+
+    // The bounds a shallow search has to beat for the node to be cut.
+    // Returns (low, high) in centi-discs of the shallow search; either is None if that side
+    // of the window is a game result, which ProbCut has nothing to say about.
+    pub fn bounds(&self, params: &ProbCutParams, alpha: &MinMaxEval, beta: &MinMaxEval) -> (Option<i32>, Option<i32>) {
+        let t = self.selectivity.t();
+        let low = if !alpha.is_proven() {
+            Some(((alpha.centi_discs() as f32 - t * params.sigma - params.b) / params.a).floor() as i32)
+        } else {
+            None
+        };
+        let high = if !beta.is_proven() {
+            Some(((beta.centi_discs() as f32 + t * params.sigma - params.b) / params.a).ceil() as i32)
+        } else {
+            None
+        };
+        (low, high)
+    }
+}
+
+impl Default for ProbCut {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+pub fn parse_params(text: &str) -> Result<Vec<ProbCutParams>, String> {
+    // one `phase depth shallow_depth a b sigma` entry per line, `#` starts a comment
+    let mut params = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 6 {
+            return Err(format!("line {}: expected 6 fields, got {}", i + 1, fields.len()));
+        }
+        let err = |e: &dyn fmt::Display| format!("line {}: {}", i + 1, e);
+        let entry = ProbCutParams {
+            phase: fields[0].parse().map_err(|e| err(&e))?,
+            depth: fields[1].parse().map_err(|e| err(&e))?,
+            shallow_depth: fields[2].parse().map_err(|e| err(&e))?,
+            a: fields[3].parse().map_err(|e| err(&e))?,
+            b: fields[4].parse().map_err(|e| err(&e))?,
+            sigma: fields[5].parse().map_err(|e| err(&e))?,
+        };
+        if entry.phase >= N_PHASES || entry.shallow_depth >= entry.depth || entry.a <= 0.0 {
+            return Err(format!("line {}: invalid parameters", i + 1));
+        }
+        params.push(entry);
+    }
+    Ok(params)
+}
+
+pub fn format_params(params: &[ProbCutParams]) -> String {
+    let mut out = String::from("# phase depth shallow_depth a b sigma\n");
+    for p in params {
+        out += &format!("{} {} {} {:.4} {:.2} {:.2}\n", p.phase, p.depth, p.shallow_depth, p.a, p.b, p.sigma);
+    }
+    out
+}
+
+pub fn default_params() -> Vec<ProbCutParams> {
+    parse_params(DEFAULT_PARAMS).expect("default ProbCut parameters are invalid")
+}
+
+// Fitted with `revello fit-probcut --positions 1000 --max-depth 8`, on the default seed and
+// heuristic evaluation
+const DEFAULT_PARAMS: &str = include_str!("probcut_params.txt");
+
+pub fn random_positions(n: usize, seed: u64) -> Vec<Board> {
+    // positions reached by random play, spread evenly over the game
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions = Vec::with_capacity(n);
+    while positions.len() < n {
+        let stop_at = rng.random_range(8..58);
+        let mut board = crate::board::board::START_BOARD;
+        while board.turn.is_some() && board.count_pieces() < stop_at {
+            let plys = possible_plys(&board).to_vec_ply();
+            board = play(&board, plys[rng.random_range(0..plys.len())]);
+        }
+        if board.turn.is_some() {
+            positions.push(board);
+        }
+    }
+    positions
+}
+
+fn search_values<E: Evaluator + Clone>(board: &Board, max_depth: u32, evaluator: &E) -> Vec<MinMaxEval> {
+    // values of searches to depth 0..=max_depth, done as iterative deepening so each
+    // search can use the transposition table of the previous one for move ordering
+    let mut transposition_table = TranspositionTable::new();
+    let mut evaluator = evaluator.clone();
+    let probcut = ProbCut::none();
+    let mut ctx = SearchContext::new(&mut transposition_table, &mut evaluator, &probcut, Instant::now(), Duration::MAX);
+    (0..=max_depth).map(|depth| {
+        min_max(board.clone(), depth, &MinMaxEval::MIN, &MinMaxEval::MAX, &mut ctx)
+            .expect("search without a time limit returned None")
+            .eval
+    }).collect()
+}
+
+pub fn fit_probcut<E: Evaluator + Clone>(positions: &[Board], max_depth: u32, evaluator: &E) -> Vec<ProbCutParams> {
+    // least squares fit of v_deep = a * v_shallow + b for every phase and depth
+    let mut samples: Vec<Vec<Vec<(f64, f64)>>> = vec![vec![Vec::new(); max_depth as usize + 1]; N_PHASES];
+
+    for board in positions {
+        let values = search_values(board, max_depth, evaluator);
+        if values.iter().any(|v| v.is_proven()) {
+            continue;
+        }
+        for depth in MIN_PROBCUT_DEPTH..=max_depth {
+            let shallow = values[shallow_depth(depth) as usize].centi_discs() as f64;
+            let deep = values[depth as usize].centi_discs() as f64;
+            samples[phase(board)][depth as usize].push((shallow, deep));
+        }
+    }
+
+    let mut params = Vec::new();
+    for (phase, per_depth) in samples.iter().enumerate() {
+        for depth in MIN_PROBCUT_DEPTH..=max_depth {
+            let points = &per_depth[depth as usize];
+            if points.len() < 10 {
+                continue;
+            }
+            let n = points.len() as f64;
+            let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+            let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+            let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+            let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+            if sxx == 0.0 {
+                continue;
+            }
+            let a = sxy / sxx;
+            if a <= 0.0 {
+                continue;
+            }
+            let b = mean_y - a * mean_x;
+            let residuals: f64 = points.iter().map(|p| (p.1 - a * p.0 - b).powi(2)).sum();
+            let sigma = (residuals / (n - 2.0)).sqrt();
+            params.push(ProbCutParams {
+                phase: phase,
+                depth: depth,
+                shallow_depth: shallow_depth(depth),
+                a: a as f32,
+                b: b as f32,
+                sigma: sigma as f32,
+            });
+        }
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_round_trip() {
+        let params = default_params();
+        assert!(!params.is_empty());
+        let reparsed = parse_params(&format_params(&params)).unwrap();
+        assert_eq!(params.len(), reparsed.len());
+        for (p, q) in params.iter().zip(reparsed.iter()) {
+            assert_eq!((p.phase, p.depth, p.shallow_depth), (q.phase, q.depth, q.shallow_depth));
+            assert!((p.a - q.a).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_bounds_widen_with_confidence() {
+        let params = ProbCutParams { phase: 0, depth: 6, shallow_depth: 3, a: 1.0, b: 0.0, sigma: 10.0 };
+        let loose = ProbCut::new(vec![params], Selectivity::new(5).unwrap());
+        let strict = ProbCut::new(vec![params], Selectivity::new(1).unwrap());
+        let (alpha, beta) = (MinMaxEval::heuristic(-20), MinMaxEval::heuristic(20));
+        let (loose_low, loose_high) = loose.bounds(&params, &alpha, &beta);
+        let (strict_low, strict_high) = strict.bounds(&params, &alpha, &beta);
+        assert!(strict_low.unwrap() < loose_low.unwrap());
+        assert!(strict_high.unwrap() > loose_high.unwrap());
+        assert_eq!(loose.bounds(&params, &MinMaxEval::MIN, &MinMaxEval::MAX), (None, None));
+    }
+
+    #[test]
+    fn test_get_fitted_depths_only() {
+        let probcut = ProbCut::with_selectivity(Selectivity::new(1).unwrap());
+        let deepest = probcut.params().iter().filter(|p| p.phase == 0).map(|p| p.depth).max().unwrap();
+        assert_eq!(probcut.get(0, deepest).unwrap().depth, deepest);
+        assert!(probcut.get(0, deepest + 1).is_none());
+        assert!(probcut.get(0, MIN_PROBCUT_DEPTH - 1).is_none());
+    }
+}
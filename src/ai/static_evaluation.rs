@@ -1,25 +1,34 @@
 use crate::ai::minmax::{MinMaxResponse, MinMaxEval};
 use crate::board::board::{Board, possible_plys, Player};
 
+pub fn final_eval(board: &Board) -> MinMaxResponse {
+    // Scores a finished game from black's point of view.
+    // Empty squares are awarded to the winner, as in tournament play.
+
+    let black_pieces: i32 = board.count_black();
+    let white_pieces: i32 = board.count_white();
+    let empties: i32 = 64 - black_pieces - white_pieces;
+
+    let discs = if black_pieces > white_pieces {
+        black_pieces - white_pieces + empties
+    }
+    else if white_pieces > black_pieces {
+        black_pieces - white_pieces - empties
+    }
+    else {
+        0
+    };
+    return MinMaxResponse::new_empty_ply(MinMaxEval::proven(discs));
+}
+
 pub fn static_eval(board: &Board) -> MinMaxResponse {
 
-    let black_pieces: i32 = board.black.count_ones().try_into().unwrap();
-    let white_pieces: i32 = board.white.count_ones().try_into().unwrap();
-
-    if board.turn == None {
-        if black_pieces - white_pieces > 0 {
-            return MinMaxResponse::new_empty_ply(MinMaxEval::new(i32::MAX - 64 + black_pieces - white_pieces));
-        }
-        else if white_pieces - black_pieces > 0 {
-            return MinMaxResponse::new_empty_ply(MinMaxEval::new(i32::MIN + 64 + black_pieces - white_pieces));
-        }
-        else {
-            return MinMaxResponse::ZERO;
-        }
+    if board.turn.is_none() {
+        return final_eval(board);
     }
 
-    let black: u64 = board.black.into();
-    let white: u64 = board.white.into();
+    let black: u64 = board.black;
+    let white: u64 = board.white;
 
     let t: u64 = 0xFFFFFFFFFFFFFF00;
     let r: u64 = 0x7F7F7F7F7F7F7F7F;
@@ -66,20 +75,20 @@ pub fn static_eval(board: &Board) -> MinMaxResponse {
     let n_white_safe: i32 = white_safe.count_ones().try_into().unwrap();
 
     let n_black_x: i32 = (
-        ((!black & 1 << 00) << 9 & black) |
-        ((!black & 1 << 07) << 7 & black) |
+        ((!black & 1) << 9 & black) |
+        ((!black & 1 << 7) << 7 & black) |
         ((!black & 1 << 56) >> 7 & black) |
         ((!black & 1 << 63) >> 9 & black)
     ).count_ones().try_into().unwrap();
 
     let n_white_x: i32 = (
-        ((!white & 1 << 00) << 9 & white) |
-        ((!white & 1 << 07) << 7 & white) |
+        ((!white & 1) << 9 & white) |
+        ((!white & 1 << 7) << 7 & white) |
         ((!white & 1 << 56) >> 7 & white) |
         ((!white & 1 << 63) >> 9 & white)
     ).count_ones().try_into().unwrap();
 
-    let player_plys: u64 = possible_plys(&board).into();
+    let player_plys: u64 = possible_plys(board).into();
 
     let mut flip_board = board.clone();
     flip_board.flip_turn();
@@ -89,10 +98,11 @@ pub fn static_eval(board: &Board) -> MinMaxResponse {
     let n_black_plys: i32 = if board.turn == Some(Player::Black) {player_plys.count_ones().try_into().unwrap()} else {opponent_plys.count_ones().try_into().unwrap()};
     let n_white_plys: i32 = if board.turn == Some(Player::White) {player_plys.count_ones().try_into().unwrap()} else {opponent_plys.count_ones().try_into().unwrap()};
 
-    let eval: i32 = n_black_plys - n_white_plys + 10 * (n_black_safe - n_white_safe) - 10 * (n_black_x - n_white_x);
+    // in centi-discs: a stable disc is worth a disc, a move a tenth of one
+    let eval: i32 = 10 * (n_black_plys - n_white_plys) + 100 * (n_black_safe - n_white_safe) - 100 * (n_black_x - n_white_x);
 
     let ret = MinMaxResponse {
-        eval: MinMaxEval {value: eval},
+        eval: MinMaxEval::heuristic(eval),
         ply: None
     };
 
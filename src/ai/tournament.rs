@@ -0,0 +1,394 @@
+// Headless matches between two players
+//
+// Games are played in pairs from the same opening, each player taking black once, so that
+// neither gets the better side of an opening more often. The games can be spread over several
+// threads; each thread builds its own players. Once few enough squares are empty, a game can
+// be adjudicated with an exact solve of the endgame instead of being played out.
+//
+// The result is reported as an Elo difference with a 95% confidence interval. A sequential
+// probability ratio test (SPRT) can stop the match as soon as it is clear whether the first
+// player is `elo0` or `elo1` Elo stronger than the second, which is how engine changes are
+// tested: run until the test accepts either hypothesis.
+
+use crate::ai::calibration::MatchScore;
+use crate::ai::evaluator::DiscCountEvaluator;
+use crate::ai::minmax::{min_max, MinMaxEval, SearchContext, SearchStats};
+use crate::ai::player::Player;
+use crate::ai::probcut::ProbCut;
+use crate::ai::static_evaluation::final_eval;
+use crate::ai::transposition_table::TranspositionTable;
+use crate::game::clock::TimeControl;
+use crate::game::result::GameResult;
+use crate::game::session::{GameSession, SessionState, TimeoutPolicy};
+use crate::board::board::{Board, Ply, START_BOARD, Player as BoardPlayer, possible_plys, play, play_moves};
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// plys played from the start position for the built in openings
+const DEFAULT_OPENING_PLYS: u32 = 4;
+
+pub struct MatchSettings {
+    pub games: u32,
+    // number of games played at the same time
+    pub concurrency: usize,
+    // time control of each side, for every game
+    pub time: TimeControl,
+    pub timeout_policy: TimeoutPolicy,
+    pub openings: Vec<Board>,
+    // solve the game exactly once this few squares are empty, 0 to play every game out
+    pub adjudicate_empties: u32,
+    pub sprt: Option<Sprt>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEnd {
+    Finished,
+    Adjudicated,
+    // this player did not answer a legal move
+    Forfeit(BoardPlayer),
+    // this player ran out of time, under `TimeoutPolicy::Lose`
+    Timeout(BoardPlayer),
+}
+
+#[derive(Clone)]
+pub struct PlayedGame {
+    pub start: Board,
+    // the board before each move, and the move
+    pub moves: Vec<(Board, Ply)>,
+    pub end: GameEnd,
+    // final disc difference for black, with the empty squares going to the winner
+    pub discs: i32,
+    // how the game ended by the rules, None when adjudicated
+    pub result: Option<GameResult>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    // chance of accepting elo1 when elo0 is true, and the other way around
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SprtDecision {
+    AcceptH0,
+    AcceptH1,
+}
+
+pub fn default_openings() -> Vec<Board> {
+    // every position a few plys into the game, in a fixed order
+    let mut seen = HashSet::new();
+    let mut positions = vec![START_BOARD];
+    for _ in 0..DEFAULT_OPENING_PLYS {
+        let mut next = Vec::new();
+        for board in &positions {
+            for ply in possible_plys(board) {
+                let child = play(board, ply);
+                if seen.insert(child.clone()) {
+                    next.push(child);
+                }
+            }
+        }
+        positions = next;
+    }
+    positions
+}
+
+pub fn parse_openings(text: &str) -> Result<Vec<Board>, String> {
+    // One opening per line, as a sequence of moves like `f5d6c3` or `f5 d6 c3`.
+    // Everything after a `#` is a comment.
+    let mut openings = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let moves: String = line.split('#').next().unwrap_or("").split_whitespace().collect();
+        if moves.is_empty() {
+            continue;
+        }
+        let board = play_moves(&START_BOARD, &moves).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        if board.turn.is_none() {
+            return Err(format!("line {}: the game is already over", number + 1));
+        }
+        openings.push(board);
+    }
+    if openings.is_empty() {
+        return Err("no openings".to_string());
+    }
+    Ok(openings)
+}
+
+// The result of an exact endgame search
+#[derive(Clone, Copy)]
+pub struct Solution {
+    // None once the game is over
+    pub ply: Option<Ply>,
+    // the disc difference for black
+    pub discs: i32,
+    pub stats: SearchStats,
+    pub time: Duration,
+}
+
+pub fn solve_position(board: &Board) -> Solution {
+    // the best move and the final score with perfect play from both sides
+    let start = Instant::now();
+    if board.turn.is_none() {
+        let discs = final_eval(board).eval.discs() as i32;
+        return Solution { ply: None, discs: discs, stats: SearchStats::default(), time: start.elapsed() };
+    }
+    let depth = 64 - board.count_pieces() as u32;
+    let mut transposition_table = TranspositionTable::new();
+    let mut evaluator = DiscCountEvaluator;
+    let probcut = ProbCut::none();
+    let mut ctx = SearchContext::new(&mut transposition_table, &mut evaluator, &probcut, start, Duration::MAX);
+    let response = min_max(board.clone(), depth, &MinMaxEval::MIN, &MinMaxEval::MAX, &mut ctx)
+        .expect("search without a time limit returned None");
+    Solution {
+        ply: response.ply,
+        discs: response.eval.discs() as i32,
+        stats: ctx.stats,
+        time: start.elapsed(),
+    }
+}
+
+pub fn solve(board: &Board) -> i32 {
+    // the disc difference for black with perfect play from both sides
+    solve_position(board).discs
+}
+
+pub fn play_game<P: Player + Clone + Send + 'static>(
+    black: &P,
+    white: &P,
+    start: &Board,
+    control: TimeControl,
+    timeout_policy: TimeoutPolicy,
+    adjudicate_empties: u32,
+) -> PlayedGame {
+    // Plays the game in a `GameSession`, with `control` for each side
+    let mut session = GameSession::new(black.clone(), white.clone(), control, control)
+        .with_timeout_policy(timeout_policy)
+        .with_position(start);
+    while !session.is_over() {
+        let board = session.board();
+        if 64 - board.count_pieces() as u32 <= adjudicate_empties {
+            let discs = solve(board);
+            return PlayedGame { start: start.clone(), moves: session.history().to_vec(), end: GameEnd::Adjudicated, discs: discs, result: None };
+        }
+        session.advance(Duration::MAX);
+    }
+    let result = session.result().expect("the game is over");
+    let end = match session.state() {
+        SessionState::TimedOut(player) => GameEnd::Timeout(player),
+        SessionState::Forfeited(player) => GameEnd::Forfeit(player),
+        SessionState::Finished | SessionState::Playing => GameEnd::Finished,
+    };
+    PlayedGame { start: start.clone(), moves: session.history().to_vec(), end: end, discs: result.margin(), result: Some(result) }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    // Elo difference for an expected score between 0 and 1
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn mean_and_variance(result: &MatchScore) -> (f64, f64) {
+    // of the score of a single game
+    let games = result.games() as f64;
+    let mean = (result.wins as f64 + 0.5 * result.draws as f64) / games;
+    let variance = (result.wins as f64 * (1.0 - mean).powi(2)
+        + result.draws as f64 * (0.5 - mean).powi(2)
+        + result.losses as f64 * mean.powi(2)) / games;
+    (mean, variance)
+}
+
+pub fn elo_difference(result: &MatchScore) -> Option<(f64, f64, f64)> {
+    // The Elo difference and the bounds of its 95% confidence interval.
+    // None without games; a player that won or lost everything is infinitely stronger or weaker.
+    if result.games() == 0 {
+        return None;
+    }
+    let (mean, variance) = mean_and_variance(result);
+    let margin = 1.96 * (variance / result.games() as f64).sqrt();
+    let clamp = |s: f64| s.clamp(0.0, 1.0);
+    Some((elo_from_score(mean), elo_from_score(clamp(mean - margin)), elo_from_score(clamp(mean + margin))))
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Sprt { elo0: elo0, elo1: elo1, alpha: 0.05, beta: 0.05 }
+    }
+
+    pub fn bounds(&self) -> (f64, f64) {
+        // the test accepts H0 below the lower and H1 above the upper bound
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+
+    pub fn llr(&self, result: &MatchScore) -> f64 {
+        // log likelihood ratio of elo1 against elo0, with the normal approximation to the
+        // distribution of the game scores
+        if result.games() == 0 {
+            return 0.0;
+        }
+        let (mean, variance) = mean_and_variance(result);
+        if variance == 0.0 {
+            return 0.0;
+        }
+        let s0 = expected_score(self.elo0);
+        let s1 = expected_score(self.elo1);
+        result.games() as f64 * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * variance)
+    }
+
+    pub fn decision(&self, result: &MatchScore) -> Option<SprtDecision> {
+        let llr = self.llr(result);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            return Some(SprtDecision::AcceptH0);
+        }
+        if llr >= upper {
+            return Some(SprtDecision::AcceptH1);
+        }
+        None
+    }
+}
+
+impl std::str::FromStr for Sprt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `elo0,elo1`
+        let (elo0, elo1) = s.split_once(',').ok_or(format!("expected elo0,elo1, got {}", s))?;
+        let elo0 = elo0.trim().parse::<f64>().map_err(|e| format!("{}: {}", elo0, e))?;
+        let elo1 = elo1.trim().parse::<f64>().map_err(|e| format!("{}: {}", elo1, e))?;
+        if elo0 >= elo1 {
+            return Err("elo0 must be smaller than elo1".to_string());
+        }
+        Ok(Sprt::new(elo0, elo1))
+    }
+}
+
+pub fn run_match<P, F, S, G>(settings: &MatchSettings, first: F, second: S, on_game: G) -> MatchScore
+where
+    P: Player + Clone + Send + 'static,
+    F: Fn() -> P + Sync,
+    S: Fn() -> P + Sync,
+    G: FnMut(u32, &PlayedGame, bool, &MatchScore) + Send,
+{
+    // Plays the match and returns the score of the first player. `first` and `second` build
+    // the players of each game, so one that failed a game (an external engine is stopped once
+    // it forfeits) starts the next afresh. `on_game` is called after every game with its
+    // number, the game, whether the first player had black and the score so far.
+    let next_game = AtomicU32::new(0);
+    let stop = AtomicBool::new(false);
+    let state = Mutex::new((MatchScore::default(), on_game));
+
+    std::thread::scope(|scope| {
+        for _ in 0..settings.concurrency.max(1) {
+            scope.spawn(|| {
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::SeqCst);
+                    if game >= settings.games || stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    let first = first();
+                    let second = second();
+                    let opening = &settings.openings[(game / 2) as usize % settings.openings.len()];
+                    let first_is_black = game.is_multiple_of(2);
+                    let (black, white) = if first_is_black { (&first, &second) } else { (&second, &first) };
+                    let played = play_game(black, white, opening, settings.time, settings.timeout_policy, settings.adjudicate_empties);
+
+                    let mut state = state.lock().unwrap();
+                    let (result, on_game) = &mut *state;
+                    result.add_game(if first_is_black { played.discs } else { -played.discs });
+                    on_game(game, &played, first_is_black, result);
+                    if settings.sprt.is_some_and(|sprt| sprt.decision(result).is_some()) {
+                        stop.store(true, Ordering::SeqCst);
+                    }
+                }
+            });
+        }
+    });
+    state.into_inner().unwrap().0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::player::{GreedyPlayer, RandomPlayer, AnyPlayer};
+    use crate::game::result::ResultReason;
+
+    #[derive(Clone)]
+    struct SlowPlayer;
+
+    impl Player for SlowPlayer {
+        fn generate_ply(&self, board: &Board, _time_left: Duration) -> Option<Ply> {
+            std::thread::sleep(Duration::from_millis(50));
+            possible_plys(board).into_iter().next()
+        }
+    }
+
+    #[test]
+    fn test_play_game_timeout() {
+        // a player over its time loses under the default policy, and plays on otherwise
+        let control = TimeControl::new(Duration::from_millis(20));
+        let played = play_game(&SlowPlayer, &SlowPlayer, &START_BOARD, control, TimeoutPolicy::Lose, 0);
+        assert_eq!(played.end, GameEnd::Timeout(BoardPlayer::Black));
+        assert!(played.moves.is_empty() && played.discs < 0);
+
+        let mut start = START_BOARD;
+        while start.turn.is_some() && start.count_pieces() < 58 {
+            start = play(&start, possible_plys(&start).into_iter().next().unwrap());
+        }
+        let played = play_game(&SlowPlayer, &SlowPlayer, &start, control, TimeoutPolicy::Ignore, 0);
+        assert_eq!(played.end, GameEnd::Finished);
+        assert_eq!(played.result.unwrap().reason, ResultReason::Normal);
+    }
+
+    #[test]
+    fn test_openings() {
+        assert!(default_openings().iter().all(|board| board.count_pieces() == 8));
+        let openings = parse_openings("f5d6 # perpendicular\n\nf5 f6\n").unwrap();
+        assert_eq!(openings.len(), 2);
+        assert!(parse_openings("f5f5").is_err());
+    }
+
+    #[test]
+    fn test_elo() {
+        let even = MatchScore { wins: 10, draws: 0, losses: 10, discs: 0 };
+        let (elo, low, high) = elo_difference(&even).unwrap();
+        assert!(elo.abs() < 1e-9 && low < 0.0 && high > 0.0);
+
+        let sprt = Sprt::new(0.0, 10.0);
+        let strong = MatchScore { wins: 300, draws: 50, losses: 100, discs: 0 };
+        assert_eq!(sprt.decision(&strong), Some(SprtDecision::AcceptH1));
+        let weak = MatchScore { wins: 100, draws: 50, losses: 300, discs: 0 };
+        assert_eq!(sprt.decision(&weak), Some(SprtDecision::AcceptH0));
+        assert_eq!(sprt.decision(&even), None);
+    }
+
+    #[test]
+    fn test_match() {
+        let settings = MatchSettings {
+            games: 4,
+            concurrency: 2,
+            time: TimeControl::new(Duration::from_secs(1)),
+            timeout_policy: TimeoutPolicy::Lose,
+            openings: default_openings(),
+            adjudicate_empties: 6,
+            sprt: None,
+        };
+        let mut games = Vec::new();
+        let result = run_match(&settings, || AnyPlayer::Greedy(GreedyPlayer), || AnyPlayer::Random(RandomPlayer), |game, played, _, _| {
+            // both play instantly and only legal moves, so neither can forfeit
+            assert!(!matches!(played.end, GameEnd::Forfeit(_)));
+            games.push(game);
+        });
+        assert_eq!(result.games(), 4);
+        games.sort();
+        assert_eq!(games, vec![0, 1, 2, 3]);
+    }
+}
@@ -1,11 +1,23 @@
-use crate::board::board::{Board, Ply, play, Player, possible_plys};
-use crate::ai::minmax::{MinMaxResponse, MinMaxEval};
+use crate::board::board::Board;
+use crate::ai::minmax::MinMaxResponse;
 use std::collections::HashMap;
 
-#[derive(Hash, PartialEq, Copy, Clone)]
+// What a stored score says about the position. A search that fails outside its alpha-beta
+// window only knows a bound on the score.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Bound {
+    Exact,
+    // the score is at least this
+    Lower,
+    // the score is at most this
+    Upper,
+}
+
+#[derive(PartialEq, Copy, Clone)]
 pub struct TranspositionEntry {
     minmax_response: MinMaxResponse,
     depth: u32,
+    bound: Bound,
 }
 
 impl TranspositionEntry {
@@ -15,13 +27,21 @@ impl TranspositionEntry {
     pub fn get_minmax_response(&self) -> MinMaxResponse {
         self.minmax_response
     }
+    pub fn get_bound(&self) -> Bound {
+        self.bound
+    }
 }
 
 impl TranspositionEntry {
     pub fn new(minmax_response: MinMaxResponse, depth: u32) -> Self{
+        return TranspositionEntry::with_bound(minmax_response, depth, Bound::Exact);
+    }
+
+    pub fn with_bound(minmax_response: MinMaxResponse, depth: u32, bound: Bound) -> Self{
         return TranspositionEntry{
             minmax_response: minmax_response,
-            depth: depth
+            depth: depth,
+            bound: bound,
         }
     }
 }
@@ -30,6 +50,12 @@ pub struct TranspositionTable {
     table: HashMap<Board, TranspositionEntry>
 }
 
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TranspositionTable {
     pub fn new() -> Self {
         return TranspositionTable {
@@ -45,43 +71,3 @@ impl TranspositionTable {
         self.table.insert(board, entry);
     }
 }
-
-pub fn move_ordering(
-    board: &Board,
-    transposition_table: &TranspositionTable,
-    depth: u32,
-) -> Vec<Ply> {
-    let plys = possible_plys(board);
-    if depth == 1 {
-        return plys.into_iter().collect();
-    }
-    if plys.is_zero() {
-        return vec![];
-    }
-    let mut scored_moves: Vec<(Ply, MinMaxEval)> = plys.into_iter().map(|ply| {
-        let new_board = play(board, ply.clone());
-        if let Some(transposition_entry) = transposition_table.get(&new_board) {
-            let eval = transposition_entry.get_minmax_response().eval;
-            return (ply, eval);
-        }
-        let eval = match board.turn {
-            Some(Player::White) => MinMaxEval::MAX,
-            Some(Player::Black) => MinMaxEval::MIN,
-            None => MinMaxEval::ZERO,
-        };
-        (ply, eval)
-    }).collect();
-
-
-    // Sort descending so best moves come first
-    if board.turn == Some(Player::Black) {
-        scored_moves.sort_by(|a, b| b.1.cmp(&a.1));
-    }
-    else if board.turn == Some(Player::White) {
-        scored_moves.sort_by(|a, b| a.1.cmp(&b.1));
-    }
-
-    let scored_moves = scored_moves.into_iter().map(|(ply, _)| ply).collect();
-
-    scored_moves
-}
@@ -0,0 +1,112 @@
+// XOT openings
+//
+// Engine games from the start position are nearly deterministic. XOT games start instead from a
+// position reached by 8 random moves that the engine judges close to even, so a match plays
+// many different games and neither colour is favoured by the opening. Openings are written one
+// per line as the moves leading to them, the format of the `--openings` files of `match`, with
+// the evaluation as a comment. A list made with `generate_xot` is bundled.
+
+use crate::board::board::{Board, Ply, START_BOARD, possible_plys, play};
+use crate::ai::evaluator::Evaluator;
+use crate::ai::player::MinMaxPlayer;
+use crate::ai::tournament::parse_openings;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+pub const XOT_MOVES: usize = 8;
+
+// Made with `revello generate-xot --count 1000 --depth 8 --max-eval 1.5`
+const BUNDLED_OPENINGS: &str = include_str!("xot_openings.txt");
+
+pub fn xot_openings() -> Vec<Board> {
+    parse_openings(BUNDLED_OPENINGS).expect("the bundled openings are legal")
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OpeningChoice {
+    Random,
+    // numbered from 1, like the lines of the list
+    Index(usize),
+}
+
+impl OpeningChoice {
+    pub fn pick(&self, openings: &[Board]) -> Result<(usize, Board), String> {
+        // the number of the opening and its position
+        let index = match self {
+            OpeningChoice::Random => rand::rng().random_range(0..openings.len()),
+            OpeningChoice::Index(number) if (1..=openings.len()).contains(number) => number - 1,
+            OpeningChoice::Index(number) => return Err(format!("no opening {}, there are {}", number, openings.len())),
+        };
+        Ok((index + 1, openings[index].clone()))
+    }
+}
+
+impl FromStr for OpeningChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "random" => Ok(OpeningChoice::Random),
+            number => number.parse().map(OpeningChoice::Index).map_err(|_| format!("Invalid opening: {}", s)),
+        }
+    }
+}
+
+pub fn generate_xot<E: Evaluator + Clone>(count: usize, depth: u32, max_eval: f32, seed: u64, player: &MinMaxPlayer<E>) -> Vec<(Vec<Ply>, f32)> {
+    // Openings of `XOT_MOVES` random moves whose best move scores at most `max_eval` discs
+    // either way, with that score for the side to move. No position comes up twice.
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut seen = HashSet::new();
+    let mut openings = Vec::with_capacity(count);
+    while openings.len() < count {
+        let mut board = START_BOARD;
+        let mut moves = Vec::with_capacity(XOT_MOVES);
+        while moves.len() < XOT_MOVES && board.turn.is_some() {
+            let plys = possible_plys(&board).to_vec_ply();
+            let ply = plys[rng.random_range(0..plys.len())];
+            board = play(&board, ply);
+            moves.push(ply);
+        }
+        if board.turn.is_none() || !seen.insert(board.clone()) {
+            continue;
+        }
+        let eval = player.analyse_moves(&board, depth)[0].1.discs();
+        if eval.abs() <= max_eval {
+            openings.push((moves, eval));
+        }
+    }
+    openings
+}
+
+pub fn format_opening(moves: &[Ply], eval: f32) -> String {
+    // a line of an openings file
+    let moves: String = moves.iter().map(|ply| ply.to_notation()).collect();
+    format!("{} # {:+.2}", moves, eval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::evaluator::DiscCountEvaluator;
+
+    #[test]
+    fn test_openings() {
+        let openings = xot_openings();
+        assert!(openings.len() >= 100);
+        assert!(openings.iter().all(|board| board.count_pieces() == 4 + XOT_MOVES as i32));
+
+        let player = MinMaxPlayer::with_evaluator(DiscCountEvaluator);
+        let generated = generate_xot(5, 2, 4.0, 1, &player);
+        assert_eq!(generated.len(), 5);
+        assert!(generated.iter().all(|(moves, eval)| moves.len() == XOT_MOVES && eval.abs() <= 4.0));
+        let text: String = generated.iter().map(|(moves, eval)| format_opening(moves, *eval) + "\n").collect();
+        assert_eq!(parse_openings(&text).unwrap().len(), 5);
+
+        assert_eq!("3".parse::<OpeningChoice>().unwrap().pick(&openings).unwrap().0, 3);
+        assert!("0".parse::<OpeningChoice>().unwrap().pick(&openings).is_err());
+        assert!("first".parse::<OpeningChoice>().is_err());
+    }
+}
@@ -21,7 +21,7 @@ pub struct Board {
     pub turn: Option<Player>,
 }
 
-#[derive(PartialEq, Clone, Copy, Hash, Eq)]
+#[derive(PartialEq, Clone, Copy, Hash, Eq, Debug)]
 pub enum Player {
     Black,
     White,
@@ -32,7 +32,7 @@ pub enum Piece {
     White
 }
 
-#[derive(Clone, PartialEq, Eq, Copy, Hash)]
+#[derive(Clone, PartialEq, Eq, Copy, Hash, Debug)]
 pub struct Ply(u64);
 
 #[derive(PartialEq)]
@@ -61,7 +61,7 @@ impl Board {
         return None
     }
     pub fn flip_turn(&mut self) {
-        if self.turn == None {
+        if self.turn.is_none() {
             return ;
         }
         self.turn = Some(!self.turn.expect("turn is None"));
@@ -85,6 +85,63 @@ impl Board {
     pub fn count_white(&self) -> i32 {
         return self.white.count_ones().try_into().unwrap();
     }
+
+    pub fn pass_if_needed(&mut self) {
+        // Passes for the side to move if it has no legal move, and ends the game if
+        // neither side has one. `play` does this after every move, boards built by hand
+        // need it before they are played on.
+        if self.turn.is_none() || !possible_plys(self).is_zero() {
+            return;
+        }
+        self.flip_turn();
+        if possible_plys(self).is_zero() {
+            self.set_turn(None);
+        }
+    }
+}
+
+// The 8 symmetries of the board are numbered by the transformations they apply, in this
+// order: bit 2 transposes along the a1-h8 diagonal, bit 1 flips the rows over, bit 0 mirrors
+// the columns. Symmetry 0 leaves the board as it is.
+pub const SYMMETRIES: usize = 8;
+
+pub fn transform(bits: u64, symmetry: usize) -> u64 {
+    let mut bits = bits;
+    if symmetry & 4 != 0 {
+        // swaps 4x4 blocks, then 2x2 blocks, then single squares across the diagonal
+        let t = 0x0f0f0f0f00000000 & (bits ^ (bits << 28));
+        bits ^= t ^ (t >> 28);
+        let t = 0x3333000033330000 & (bits ^ (bits << 14));
+        bits ^= t ^ (t >> 14);
+        let t = 0x5500550055005500 & (bits ^ (bits << 7));
+        bits ^= t ^ (t >> 7);
+    }
+    if symmetry & 2 != 0 {
+        bits = bits.swap_bytes();
+    }
+    if symmetry & 1 != 0 {
+        bits = bits.reverse_bits().swap_bytes();
+    }
+    bits
+}
+
+impl Board {
+    pub fn symmetry(&self, symmetry: usize) -> Board {
+        Board {
+            black: transform(self.black, symmetry),
+            white: transform(self.white, symmetry),
+            turn: self.turn,
+        }
+    }
+
+    pub fn canonical(&self) -> (Board, usize) {
+        // the smallest of the symmetric boards, the same for all of them, and the symmetry
+        // that turns this board into it
+        (0..SYMMETRIES)
+            .map(|symmetry| (self.symmetry(symmetry), symmetry))
+            .min_by_key(|(board, _)| (board.black, board.white))
+            .expect("there are 8 symmetries")
+    }
 }
 
 pub const START_BOARD: Board = Board {
@@ -93,7 +150,48 @@ pub const START_BOARD: Board = Board {
     turn: Some(Player::Black),
 };
 
+// the parallel start, with the two black discs side by side on d4 and e4 instead of across
+// the centre
+pub const PARALLEL_START_BOARD: Board = Board {
+    black: 0x0000000018000000,
+    white: 0x0000001800000000,
+    turn: Some(Player::Black),
+};
+
+pub fn parse_position(s: &str) -> Result<Board, String> {
+    // the named starting setups, or a board in a form `Board` parses
+    match s.to_lowercase().as_str() {
+        "standard" => Ok(START_BOARD),
+        "parallel" => Ok(PARALLEL_START_BOARD),
+        _ => s.parse(),
+    }
+}
+
+pub fn parse_moves(board: &Board, moves: &str) -> Result<Vec<Ply>, String> {
+    // The moves of a sequence like `f5d6c3` or `f5 d6 c3` played from `board`, checked to be legal
+    let moves: String = moves.split_whitespace().collect();
+    let chars: Vec<char> = moves.chars().collect();
+    let mut board = board.clone();
+    let mut plys = Vec::new();
+    for (i, notation) in chars.chunks(2).enumerate() {
+        let notation: String = notation.iter().collect();
+        let ply = Ply::from_notation(&notation)
+            .filter(|ply| ply.is_in(possible_plys(&board)))
+            .ok_or(format!("move {}: illegal move {}", i + 1, notation))?;
+        board = play(&board, ply);
+        plys.push(ply);
+    }
+    Ok(plys)
+}
+
+pub fn play_moves(board: &Board, moves: &str) -> Result<Board, String> {
+    // Plays a sequence of moves from `board`, passing where needed
+    let plys = parse_moves(board, moves)?;
+    Ok(plys.iter().fold(board.clone(), |board, ply| play(&board, *ply)))
+}
+
 use std::fmt;
+use std::str::FromStr;
 impl fmt::Display for Board {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "Turn: {}", if self.turn == Some(Player::Black) { "Black" } else if self.turn == Some(Player::White) { "White"} else { "None" })?;
@@ -116,6 +214,57 @@ impl fmt::Display for Board {
     }
 }
 
+impl FromStr for Board {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Reads the diagram printed by `Display`, or the 64 squares row by row followed by the
+        // side to move, like `---------------------------OX------XO--------------------------- X`.
+        // Black is `B`, `X` or `*`, white `W` or `O`, empty squares `.` or `-`, and a side to
+        // move of `-` means the game is over.
+        let mut turn = None;
+        let mut squares = Vec::new();
+        for line in s.lines() {
+            if let Some(side) = line.trim().strip_prefix("Turn:") {
+                turn = Some(match side.trim() {
+                    "Black" => Some(Player::Black),
+                    "White" => Some(Player::White),
+                    "None" => None,
+                    side => return Err(format!("invalid side to move {:?}", side)),
+                });
+            } else {
+                squares.extend(line.chars().filter(|c| !c.is_whitespace()));
+            }
+        }
+        let turn = match turn {
+            Some(turn) => turn,
+            // the compact form ends with the side to move
+            None if squares.len() == 65 => match squares.pop() {
+                Some('B' | 'b' | 'X' | 'x' | '*') => Some(Player::Black),
+                Some('W' | 'w' | 'O' | 'o') => Some(Player::White),
+                Some('-') => None,
+                side => return Err(format!("invalid side to move {:?}", side.unwrap_or(' '))),
+            },
+            None => return Err("missing the side to move".to_string()),
+        };
+        if squares.len() != 64 {
+            return Err(format!("a board has 64 squares, found {}", squares.len()));
+        }
+
+        let mut black = 0u64;
+        let mut white = 0u64;
+        for (index, c) in squares.iter().enumerate() {
+            match c {
+                'B' | 'b' | 'X' | 'x' | '*' => black |= 1 << index,
+                'W' | 'w' | 'O' | 'o' => white |= 1 << index,
+                '.' | '-' => {},
+                _ => return Err(format!("invalid square {:?}", c)),
+            }
+        }
+        Ok(Board::new(black, white, turn).expect("a square holds one disc"))
+    }
+}
+
 impl fmt::Display for Ply {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for i in 0..8 {
@@ -172,6 +321,9 @@ impl Ply {
         None
     }
 
+    /// # Safety
+    ///
+    /// `ply` must have at most one bit set.
     pub const unsafe fn new_unchecked(ply: u64) -> Self {
         Self(ply)
     }
@@ -186,6 +338,27 @@ impl Ply {
     pub fn is_in(&self, plys: Plys) -> bool {
         return self.0 & plys.0 != 0;
     }
+
+    pub fn from_notation(notation: &str) -> Option<Self> {
+        // Parses a square in algebraic notation, columns a-h from the left and rows 1-8 from
+        // the top, such as "f5" or "F5"
+        let mut chars = notation.trim().chars();
+        let col = chars.next()?.to_ascii_lowercase();
+        let row = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&col) || !('1'..='8').contains(&row) {
+            return None;
+        }
+        return Self::from_row_col(row as usize - '1' as usize, col as usize - 'a' as usize);
+    }
+
+    pub fn to_notation(&self) -> String {
+        let (row, col) = self.to_row_col();
+        return format!("{}{}", (b'a' + col as u8) as char, row + 1);
+    }
+
+    pub fn symmetry(&self, symmetry: usize) -> Ply {
+        Ply(transform(self.0, symmetry))
+    }
 }
 
 impl Plys {
@@ -253,7 +426,7 @@ impl From<Plys> for u64 {
 
 
 pub fn possible_plys(board: &Board) -> Plys {
-    if board.turn == None {
+    if board.turn.is_none() {
         return Plys::new(0);
     }
     let player = if board.turn.expect("turn is None") == Player::Black { board.black } else { board.white};
@@ -315,14 +488,10 @@ pub fn play(board: &Board, ply: Ply) -> Board {
     // Returns:
     // - The new board state
 
-    if board.turn == None {
+    if board.turn.is_none() {
         return board.clone();
     }
 
-    if possible_plys(board) == Plys(0) {
-
-    }
-
     let player = if board.turn.expect("Turn is None") == Player::Black { board.black } else { board.white};
     let opponent = if board.turn.expect("Turn is None") == Player::Black { board.white } else { board.black };
 
@@ -440,6 +609,69 @@ pub fn play(board: &Board, ply: Ply) -> Board {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_notation() {
+        assert!(Ply::from_notation("a1") == Ply::from_row_col(0, 0));
+        assert!(Ply::from_notation("F5") == Ply::from_row_col(4, 5));
+        assert_eq!(Ply::from_row_col(7, 7).unwrap().to_notation(), "h8");
+        assert!(Ply::from_notation("i1").is_none());
+        assert!(Ply::from_notation("a9").is_none());
+        assert!(Ply::from_notation("a10").is_none());
+        for ply in possible_plys(&START_BOARD) {
+            assert!(Ply::from_notation(&ply.to_notation()) == Some(ply));
+        }
+    }
+
+    #[test]
+    fn test_parse_board() {
+        assert!(START_BOARD.to_string().parse::<Board>().unwrap() == START_BOARD);
+        let board = play(&play(&START_BOARD, Ply::from_notation("f5").unwrap()), Ply::from_notation("d6").unwrap());
+        assert!(board.to_string().parse::<Board>().unwrap() == board);
+
+        let compact = "---------------------------OX------XO--------------------------- X";
+        assert!(compact.parse::<Board>().unwrap() == START_BOARD);
+        let over: Board = compact.replace(" X", "-").parse().unwrap();
+        assert!(over.turn.is_none() && over.black == START_BOARD.black);
+
+        assert!("Turn: Black\nB W".parse::<Board>().is_err());
+        assert!(compact.replace(" X", "").parse::<Board>().is_err());
+        assert!(compact.replace('-', "?").parse::<Board>().is_err());
+    }
+
+    #[test]
+    fn test_play_moves() {
+        let board = play_moves(&START_BOARD, "f5 d6c3").unwrap();
+        assert_eq!(board.count_pieces(), 7);
+        assert!(board.turn == Some(Player::White));
+        assert_eq!(play_moves(&START_BOARD, "f5f5").err().unwrap(), "move 2: illegal move f5");
+
+        // on the parallel start black plays below the white pair
+        let moves: Vec<String> = possible_plys(&PARALLEL_START_BOARD).into_iter().map(|ply| ply.to_notation()).collect();
+        assert_eq!(moves, ["c6", "d6", "e6", "f6"]);
+        assert!(play_moves(&PARALLEL_START_BOARD, "d6").is_ok());
+    }
+
+    #[test]
+    fn test_symmetries() {
+        let a1 = Ply::from_notation("a1").unwrap();
+        let b1 = Ply::from_notation("b1").unwrap();
+        assert_eq!(b1.symmetry(4).to_notation(), "a2");
+        assert_eq!(b1.symmetry(2).to_notation(), "b8");
+        assert_eq!(b1.symmetry(1).to_notation(), "g1");
+        assert_eq!(a1.symmetry(7).to_notation(), "h8");
+
+        let board = play_moves(&START_BOARD, "f5d6c3d3c4").unwrap();
+        let (canonical, _) = board.canonical();
+        for symmetry in 0..SYMMETRIES {
+            let image = board.symmetry(symmetry);
+            assert!(image.canonical().0 == canonical);
+            // moves turn with the board
+            let moves: Vec<Ply> = possible_plys(&board).into_iter().map(|ply| ply.symmetry(symmetry)).collect();
+            assert!(possible_plys(&image).into_iter().all(|ply| moves.contains(&ply)));
+        }
+        assert!(START_BOARD.symmetry(4) == START_BOARD);
+    }
+
     #[test]
     fn test_move_gen() {
         let board = START_BOARD;
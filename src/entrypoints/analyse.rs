@@ -0,0 +1,112 @@
+// Game analysis
+//
+// Replays a game and searches every position to a fixed depth, to show how each move compares
+// with the best one, after the name of the opening if it has one. Scores are in discs for the
+// player making the move; the loss of a move is how much worse it scores than the best move.
+// The totals per player add the losses up.
+
+use crate::ai::evaluator::Evaluator;
+use crate::ai::player::MinMaxPlayer;
+use crate::board::board::Player;
+use crate::formats::ggf::{GgfGame, parse_games};
+use crate::game::openings::opening_names;
+
+use std::io::{self, Write};
+
+// losses smaller than this are rounding, not mistakes
+const MISTAKE_THRESHOLD: f32 = 0.01;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct PlayerAnalysis {
+    pub mistakes: u32,
+    pub discs_lost: f32,
+}
+
+pub fn analyse_game<E, W>(game: &GgfGame, player: &MinMaxPlayer<E>, depth: u32, output: &mut W) -> io::Result<[PlayerAnalysis; 2]>
+where
+    E: Evaluator + Clone,
+    W: Write,
+{
+    // Writes one line per move and returns the analysis of black and white
+    let positions = game.positions().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let final_board = game.final_board().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let boards = positions.iter().map(|(board, _)| board).chain(std::iter::once(&final_board));
+    if let Some(name) = opening_names().name_of_game(boards) {
+        writeln!(output, "Opening: {}", name)?;
+    }
+    let mut analysis = [PlayerAnalysis::default(); 2];
+    for (i, (board, ggf_move)) in positions.iter().enumerate() {
+        let color = if ggf_move.player == Player::Black { 'B' } else { 'W' };
+        let Some(ply) = ggf_move.ply else {
+            writeln!(output, "{:>3}. {} PA", i + 1, color)?;
+            continue;
+        };
+
+        let scores = player.analyse_moves(board, depth);
+        let (best_ply, best) = scores[0];
+        let played = scores.iter()
+            .find(|(scored, _)| *scored == ply)
+            .map(|(_, eval)| *eval)
+            .expect("a legal move is scored");
+        let loss = best.discs() - played.discs();
+        write!(
+            output,
+            "{:>3}. {} {}  {:+7.2}   best {}  {:+7.2}",
+            i + 1, color, ply.to_notation().to_uppercase(), played.discs(), best_ply.to_notation().to_uppercase(), best.discs(),
+        )?;
+        if loss >= MISTAKE_THRESHOLD {
+            write!(output, "   loss {:.2}", loss)?;
+            let side = &mut analysis[if ggf_move.player == Player::Black { 0 } else { 1 }];
+            side.mistakes += 1;
+            side.discs_lost += loss;
+        }
+        writeln!(output)?;
+    }
+
+    for (name, side) in ["Black", "White"].iter().zip(analysis.iter()) {
+        writeln!(output, "{}: {} mistakes, {:.2} discs lost", name, side.mistakes, side.discs_lost)?;
+    }
+    Ok(analysis)
+}
+
+pub fn analyse_file<E, W>(path: &str, player: &MinMaxPlayer<E>, depth: u32, output: &mut W) -> Result<(), String>
+where
+    E: Evaluator + Clone,
+    W: Write,
+{
+    // Analyses every game of a GGF file, each after a line naming its players
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let games = parse_games(&text).map_err(|e| format!("{}: {}", path, e))?;
+    for (i, game) in games.iter().enumerate() {
+        let header = writeln!(output, "Game {}: {} vs {}", i + 1, game.tag("PB").unwrap_or("?"), game.tag("PW").unwrap_or("?"));
+        header.and_then(|_| analyse_game(game, player, depth, output)).map_err(|e| format!("{}: game {}: {}", path, i + 1, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::evaluator::DiscCountEvaluator;
+    use crate::formats::ggf::parse_game;
+
+    #[test]
+    fn test_analyse_game() {
+        // counting discs one move ahead, d3 flips 2 discs fewer than g5
+        let game = parse_game("(;GM[Othello]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[F5]W[D6]B[C3]W[D3];)").unwrap();
+        let player = MinMaxPlayer::with_evaluator(DiscCountEvaluator);
+        let mut output = Vec::new();
+        let analysis = analyse_game(&game, &player, 1, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[0], "Opening: Perpendicular opening");
+        assert!(lines[1].starts_with("  1. B F5"));
+        assert!(lines[4].starts_with("  4. W D3"));
+        assert!(lines[5].starts_with("Black: "));
+        assert!(lines[4].ends_with("best G5    +2.00   loss 2.00"));
+        assert_eq!(analysis[0].mistakes, 0);
+        assert_eq!((analysis[1].mistakes, analysis[1].discs_lost), (1, 2.0));
+    }
+}
@@ -0,0 +1,164 @@
+// Game databases
+//
+// `db import` converts WTHOR databases into GGF records, `db index` builds a position index
+// from GGF files, WTHOR databases and scoresheets, and `db query` prints what an index knows
+// about a position. A game that cannot be replayed is reported on stderr and skipped.
+
+use crate::board::board::{Board, Player};
+use crate::entrypoints::transcript::transcript_game;
+use crate::formats::ggf::{GgfGame, parse_games};
+use crate::formats::position_db::{PositionDb, Outcomes};
+use crate::formats::transcript::Transcript;
+use crate::formats::wthor;
+
+use std::io::{self, Write};
+
+fn read_bytes(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn read_names(path: Option<&str>, size: usize) -> Result<Vec<String>, String> {
+    // the player or tournament names of a WTHOR file, none without a file
+    match path {
+        Some(path) => wthor::parse_names(&read_bytes(path)?, size).map_err(|e| format!("{}: {}", path, e)),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn wthor_games(path: &str, players: &[String], tournaments: &[String]) -> Result<(Vec<GgfGame>, u32), String> {
+    // The games of a WTHOR database as GGF, and how many were skipped. A few archived games
+    // have illegal moves; each is reported on stderr.
+    let (header, games) = wthor::parse_games(&read_bytes(path)?).map_err(|e| format!("{}: {}", path, e))?;
+    let (mut ggf_games, mut skipped) = (Vec::new(), 0);
+    for (i, game) in games.iter().enumerate() {
+        match game.to_ggf(&header, players, tournaments) {
+            Ok(ggf) => ggf_games.push(ggf),
+            Err(e) => {
+                eprintln!("{}: game {}: {}", path, i + 1, e);
+                skipped += 1;
+            }
+        }
+    }
+    Ok((ggf_games, skipped))
+}
+
+pub fn import_wthor<W: Write>(files: &[String], players: &[String], tournaments: &[String], output: &mut W) -> Result<(u32, u32), String> {
+    // Writes the games of the databases as GGF, one per line, and returns how many were
+    // imported and how many skipped
+    let (mut imported, mut skipped) = (0, 0);
+    for path in files {
+        let (games, file_skipped) = wthor_games(path, players, tournaments)?;
+        for ggf in &games {
+            writeln!(output, "{}", ggf).map_err(|e| e.to_string())?;
+            imported += 1;
+        }
+        skipped += file_skipped;
+    }
+    Ok((imported, skipped))
+}
+
+pub fn read_games(path: &str, players: &[String]) -> Result<(Vec<GgfGame>, u32), String> {
+    // The games of a GGF file, a WTHOR database or a scoresheet, told apart by the extension,
+    // and how many games of a database were skipped
+    if path.ends_with(".wtb") {
+        return wthor_games(path, players, &[]);
+    }
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let games = if path.ends_with(".ggf") {
+        parse_games(&text)
+    } else {
+        text.parse::<Transcript>().and_then(|transcript| Ok(vec![transcript_game(&transcript, "?", "?")?]))
+    };
+    games.map(|games| (games, 0)).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn index_games(files: &[String], players: &[String]) -> Result<(PositionDb, u32), String> {
+    // the index of the games of the files, and how many games were skipped
+    let (mut db, mut skipped) = (PositionDb::new(), 0);
+    for path in files {
+        let (games, file_skipped) = read_games(path, players)?;
+        skipped += file_skipped;
+        for (i, game) in games.iter().enumerate() {
+            if let Err(e) = db.add_game(game) {
+                eprintln!("{}: game {}: {}", path, i + 1, e);
+                skipped += 1;
+            }
+        }
+    }
+    Ok((db, skipped))
+}
+
+pub fn read_db(path: &str) -> Result<PositionDb, String> {
+    PositionDb::from_bytes(&read_bytes(path)?).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn write_query<W: Write>(db: &PositionDb, board: &Board, games: usize, output: &mut W) -> io::Result<()> {
+    // The games that reached the position, how each next move fared and the first `games`
+    // games, with the results for the side to move
+    let Some(stats) = db.query(board) else {
+        return writeln!(output, "No game reached this position");
+    };
+
+    let summary = |outcomes: &Outcomes| match (outcomes.win_rate(), outcomes.average_score()) {
+        (Some(rate), Some(score)) => format!("{:>5.1}% won  {:+6.1} discs", 100.0 * rate, score),
+        _ => "no results".to_string(),
+    };
+    let side = match board.turn {
+        Some(Player::White) => "white",
+        _ => "black",
+    };
+    writeln!(output, "{} games, for {}: {}", stats.games.len(), side, summary(&stats.outcomes))?;
+    for (ply, outcomes) in &stats.moves {
+        writeln!(output, "  {}  {:>6} games  {}", ply.to_notation().to_uppercase(), outcomes.games, summary(outcomes))?;
+    }
+    for number in stats.games.iter().take(games) {
+        let game = &db.games[*number as usize];
+        let result = game.result.map_or("?".to_string(), |discs| format!("{:+}", discs));
+        writeln!(output, "  #{:<6} {} vs {}  {}", number + 1, game.black, game.white, result)?;
+    }
+    if stats.games.len() > games {
+        writeln!(output, "  and {} more", stats.games.len() - games)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wthor_file(name: &str, games: &[&[u8]]) -> String {
+        // a database of `games`, each given by its moves, written to a temporary file
+        let mut bytes = vec![20, 24, 3, 1];
+        bytes.extend((games.len() as u32).to_le_bytes());
+        bytes.extend(0u16.to_le_bytes());
+        bytes.extend(2024u16.to_le_bytes());
+        bytes.extend([8, 0, 22, 0]);
+        for moves in games {
+            let mut record = vec![0, 0, 0, 0, 0, 0, 32, 32];
+            record.extend(*moves);
+            record.resize(68, 0);
+            bytes.extend(record);
+        }
+        let path = std::env::temp_dir().join(format!("revello-{}-{}.wtb", name, std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_skipped_games() {
+        // f5 d6 c3, then a game with an illegal a1
+        let path = wthor_file("skipped", &[&[56, 64, 33], &[56, 11]]);
+        let files = vec![path.clone()];
+
+        let (games, skipped) = read_games(&path, &[]).unwrap();
+        assert_eq!((games.len(), skipped), (1, 1));
+
+        let mut output = Vec::new();
+        assert_eq!(import_wthor(&files, &[], &[], &mut output).unwrap(), (1, 1));
+        assert_eq!(String::from_utf8(output).unwrap().lines().count(), 1);
+
+        let (db, skipped) = index_games(&files, &[]).unwrap();
+        assert_eq!((db.games.len(), skipped), (1, 1));
+        std::fs::remove_file(path).unwrap();
+    }
+}
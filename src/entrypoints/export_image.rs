@@ -0,0 +1,38 @@
+// Image export
+//
+// Draws a position, or a game up to some move, as an SVG or PNG diagram written to a file.
+
+use crate::board::board::{Board, Ply};
+use crate::entrypoints::db::read_games;
+use crate::formats::diagram::Diagram;
+use crate::formats::transcript::Transcript;
+
+pub fn game_moves(path: &str, number: usize) -> Result<(Board, Vec<Ply>), String> {
+    // the start and the moves of game `number`, from 1, of a file `read_games` can read
+    let (games, _) = read_games(path, &[])?;
+    let game = games.get(number.wrapping_sub(1)).ok_or(format!("{}: no game {}, there are {}", path, number, games.len()))?;
+    let positions = game.positions().map_err(|e| format!("{}: game {}: {}", path, number, e))?;
+    Ok((game.start.clone(), positions.iter().filter_map(|(_, ggf_move)| ggf_move.ply).collect()))
+}
+
+pub fn game_diagram(start: &Board, moves: &[Ply], legal_moves: bool, numbers: bool, square_size: u32) -> Diagram {
+    // the board after the moves with the last one marked, and the move numbers if asked
+    let mut diagram = Diagram::from_game(start, moves).with_square_size(square_size);
+    if legal_moves {
+        diagram = diagram.with_legal_moves();
+    }
+    if numbers {
+        diagram = diagram.with_numbers(Transcript::from_moves(start, moves).numbers);
+    }
+    diagram
+}
+
+pub fn write_image(diagram: &Diagram, path: &str) -> Result<(), String> {
+    // PNG if the name ends in .png, SVG otherwise
+    let written = if path.to_lowercase().ends_with(".png") {
+        std::fs::write(path, diagram.to_png()?)
+    } else {
+        std::fs::write(path, diagram.to_svg())
+    };
+    written.map_err(|e| format!("{}: {}", path, e))
+}
@@ -0,0 +1,304 @@
+// GTP (Go Text Protocol)
+//
+// Generic board game front-ends drive Othello engines with the commands of GTP version 2.
+// Every command may start with a numeric id, and is answered with `=[id] result` or
+// `?[id] error`, followed by an empty line.
+//
+// Vertices use the Othello notation: columns a to h from the left, rows 1 to 8 from the top,
+// and `pass`. Passes are played automatically by `Board`, so `play <color> pass` is only
+// accepted when that color has no move, and `genmove` answers `pass` when it is not the
+// engine's turn. Byo-yomi is simply added to the main time.
+
+use crate::ai::player::Player;
+use crate::board::board::{Board, Ply, START_BOARD, Player as BoardPlayer, possible_plys, play};
+use crate::ai::static_evaluation::final_eval;
+
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+pub const ENGINE_NAME: &str = "Revello";
+
+const KNOWN_COMMANDS: [&str; 16] = [
+    "protocol_version", "name", "version", "known_command", "list_commands", "quit",
+    "boardsize", "clear_board", "komi", "play", "genmove", "undo",
+    "time_settings", "time_left", "showboard", "final_score",
+];
+
+pub struct GtpEngine<P: Player> {
+    player: P,
+    board: Board,
+    // positions before each move, for undo
+    history: Vec<Board>,
+    // time per side when the front-end sets no time limit
+    default_time: Duration,
+    // main time given by time_settings, in use after clear_board
+    main_time: Duration,
+    byo_yomi_time: Duration,
+    black_time: Duration,
+    white_time: Duration,
+}
+
+fn parse_color(text: &str) -> Result<BoardPlayer, String> {
+    match text.to_lowercase().as_str() {
+        "b" | "black" => Ok(BoardPlayer::Black),
+        "w" | "white" => Ok(BoardPlayer::White),
+        _ => Err("invalid color".to_string()),
+    }
+}
+
+fn parse_vertex(text: &str) -> Result<Option<Ply>, String> {
+    // None is a pass
+    if text.eq_ignore_ascii_case("pass") {
+        return Ok(None);
+    }
+    Ply::from_notation(text).map(Some).ok_or("invalid coordinate".to_string())
+}
+
+fn parse_seconds(text: &str) -> Result<Duration, String> {
+    text.parse::<u64>().map(Duration::from_secs).map_err(|_| format!("invalid time: {}", text))
+}
+
+fn show_board(board: &Board) -> String {
+    let mut text = String::from("\n   A B C D E F G H\n");
+    for row in 0..8 {
+        text += &format!("{:>2}", row + 1);
+        for col in 0..8 {
+            let mask = 1u64 << (row * 8 + col);
+            let square = if board.black & mask != 0 { 'X' } else if board.white & mask != 0 { 'O' } else { '.' };
+            text.push(' ');
+            text.push(square);
+        }
+        text.push('\n');
+    }
+    let turn = match board.turn {
+        Some(BoardPlayer::Black) => "Black (X) to move",
+        Some(BoardPlayer::White) => "White (O) to move",
+        None => "Game over",
+    };
+    text += &format!("{}, X {} O {}", turn, board.count_black(), board.count_white());
+    text
+}
+
+impl<P: Player> GtpEngine<P> {
+    pub fn new(player: P, time: Duration) -> Self {
+        let mut engine = GtpEngine {
+            player: player,
+            board: START_BOARD,
+            history: Vec::new(),
+            default_time: time,
+            main_time: time,
+            byo_yomi_time: Duration::ZERO,
+            black_time: time,
+            white_time: time,
+        };
+        engine.player.update(&engine.board);
+        engine
+    }
+
+    fn clock(&mut self, color: BoardPlayer) -> &mut Duration {
+        match color {
+            BoardPlayer::Black => &mut self.black_time,
+            BoardPlayer::White => &mut self.white_time,
+        }
+    }
+
+    fn set_board(&mut self, board: Board) {
+        self.history.push(self.board.clone());
+        self.board = board;
+        self.player.update(&self.board);
+    }
+
+    fn play_move(&mut self, color: BoardPlayer, ply: Option<Ply>) -> Result<(), String> {
+        match ply {
+            // the board has already passed if the color has no move
+            None if self.board.turn != Some(color) => {
+                self.set_board(self.board.clone());
+                Ok(())
+            }
+            Some(ply) if self.board.turn == Some(color) && ply.is_in(possible_plys(&self.board)) => {
+                self.set_board(play(&self.board, ply));
+                Ok(())
+            }
+            _ => Err("illegal move".to_string()),
+        }
+    }
+
+    fn generate_move(&mut self, color: BoardPlayer) -> String {
+        if self.board.turn != Some(color) {
+            self.set_board(self.board.clone());
+            return "pass".to_string();
+        }
+        let time_left = self.clock(color).saturating_add(self.byo_yomi_time);
+        let start = Instant::now();
+        let ply = self.player.generate_ply(&self.board, time_left);
+        let clock = self.clock(color);
+        *clock = clock.saturating_sub(start.elapsed());
+
+        match ply {
+            Some(ply) if ply.is_in(possible_plys(&self.board)) => {
+                self.set_board(play(&self.board, ply));
+                ply.to_notation()
+            }
+            // never answer with an illegal move, resign instead
+            _ => "resign".to_string(),
+        }
+    }
+
+    fn final_score(&self) -> String {
+        // empty squares go to the winner once the game is over
+        let discs = match self.board.turn {
+            None => final_eval(&self.board).eval.discs() as i32,
+            Some(_) => self.board.count_black() - self.board.count_white(),
+        };
+        match discs {
+            0 => "0".to_string(),
+            discs if discs > 0 => format!("B+{}", discs),
+            discs => format!("W+{}", -discs),
+        }
+    }
+
+    // Runs one command, returns the response or the error message
+    pub fn execute(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        match (command, args) {
+            ("protocol_version", _) => Ok("2".to_string()),
+            ("name", _) => Ok(ENGINE_NAME.to_string()),
+            ("version", _) => Ok(env!("CARGO_PKG_VERSION").to_string()),
+            ("known_command", [name]) => Ok(KNOWN_COMMANDS.contains(name).to_string()),
+            ("list_commands", _) => Ok(KNOWN_COMMANDS.join("\n")),
+            ("quit", _) => Ok(String::new()),
+            ("boardsize", [size]) => match size.parse::<u32>() {
+                Ok(8) => Ok(String::new()),
+                _ => Err("unacceptable size".to_string()),
+            },
+            ("clear_board", _) => {
+                self.board = START_BOARD;
+                self.history.clear();
+                self.black_time = self.main_time;
+                self.white_time = self.main_time;
+                self.player.update(&self.board);
+                Ok(String::new())
+            }
+            // there is no komi in Othello
+            ("komi", [_]) => Ok(String::new()),
+            ("play", [color, vertex]) => {
+                let color = parse_color(color)?;
+                let ply = parse_vertex(vertex)?;
+                self.play_move(color, ply)?;
+                Ok(String::new())
+            }
+            ("genmove", [color]) => {
+                let color = parse_color(color)?;
+                Ok(self.generate_move(color))
+            }
+            ("undo", []) => {
+                let board = self.history.pop().ok_or("cannot undo")?;
+                self.board = board;
+                self.player.update(&self.board);
+                Ok(String::new())
+            }
+            ("time_settings", [main_time, byo_yomi_time, byo_yomi_stones]) => {
+                let main_time = parse_seconds(main_time)?;
+                let byo_yomi_time = parse_seconds(byo_yomi_time)?;
+                let stones = byo_yomi_stones.parse::<u32>().map_err(|_| "invalid number of stones")?;
+                // byo-yomi time without stones means no time limit
+                let untimed = stones == 0 && !byo_yomi_time.is_zero();
+                self.main_time = if untimed { self.default_time } else { main_time };
+                self.byo_yomi_time = if untimed { Duration::ZERO } else { byo_yomi_time };
+                self.black_time = self.main_time;
+                self.white_time = self.main_time;
+                Ok(String::new())
+            }
+            ("time_left", [color, time, _stones]) => {
+                let color = parse_color(color)?;
+                *self.clock(color) = parse_seconds(time)?;
+                Ok(String::new())
+            }
+            ("showboard", _) => Ok(show_board(&self.board)),
+            ("final_score", _) => Ok(self.final_score()),
+            _ if KNOWN_COMMANDS.contains(&command) => Err("syntax error".to_string()),
+            _ => Err("unknown command".to_string()),
+        }
+    }
+}
+
+pub fn run_gtp<P, R, W>(player: P, time: Duration, input: R, mut output: W) -> io::Result<()>
+where
+    P: Player,
+    R: BufRead,
+    W: Write,
+{
+    // Answers commands until `quit` or until the input is closed
+    let mut engine = GtpEngine::new(player, time);
+    for line in input.lines() {
+        let line = line?;
+        // comments and control characters are dropped, tabs are spaces
+        let line: String = line.split('#').next().unwrap_or("")
+            .chars()
+            .map(|c| if c == '\t' { ' ' } else { c })
+            .filter(|c| !c.is_control())
+            .collect();
+        let mut words: Vec<&str> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let id = if words[0].parse::<u32>().is_ok() { words.remove(0) } else { "" };
+        let Some((&command, args)) = words.split_first() else {
+            continue;
+        };
+
+        match engine.execute(command, args) {
+            Ok(response) => write!(output, "={} {}\n\n", id, response)?,
+            Err(error) => write!(output, "?{} {}\n\n", id, error)?,
+        }
+        output.flush()?;
+        if command == "quit" {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::player::GreedyPlayer;
+
+    fn session(script: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        run_gtp(GreedyPlayer, Duration::from_secs(10), script.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+            .split("\n\n")
+            .filter(|response| !response.is_empty())
+            .map(|response| response.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_session() {
+        let responses = session("\
+1 boardsize 8
+play b f5 # a comment
+play b f6
+play w f6
+2 genmove b
+undo
+undo
+play w pass
+final_score
+quit
+showboard
+");
+        assert_eq!(responses[0], "=1 ");
+        assert_eq!(responses[1], "= ");
+        assert_eq!(responses[2], "? illegal move");
+        assert_eq!(responses[3], "= ");
+        assert!(responses[4].starts_with("=2 "));
+        assert_eq!(responses[5], "= ");
+        assert_eq!(responses[6], "= ");
+        // white can move
+        assert_eq!(responses[7], "? illegal move");
+        assert_eq!(responses[8], "= B+3");
+        // nothing is answered after quit
+        assert_eq!(responses.len(), 10);
+    }
+}
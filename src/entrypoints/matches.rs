@@ -0,0 +1,87 @@
+// Matches
+//
+// Runs a match between two players without a window, printing every game as it ends with the
+// score so far, then the final score, the Elo difference and the SPRT decision. The games can
+// also be written to a GGF file as they finish.
+
+use crate::ai::calibration::MatchScore;
+use crate::ai::player::Player;
+use crate::ai::tournament::{MatchSettings, SprtDecision, GameEnd, run_match, default_openings, parse_openings, elo_difference};
+use crate::ai::xot::xot_openings;
+use crate::board::board::Board;
+use crate::formats::ggf::GgfGame;
+
+use std::io::{self, Write};
+
+pub fn match_openings(position: Option<&Board>, xot: bool, path: Option<&str>) -> Result<Vec<Board>, String> {
+    // a single position, the XOT openings, the openings of a file or the built in ones
+    if let Some(position) = position {
+        return Ok(vec![position.clone()]);
+    }
+    if xot {
+        return Ok(xot_openings());
+    }
+    match path {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+            parse_openings(&text).map_err(|e| format!("{}: {}", path, e))
+        }
+        None => Ok(default_openings()),
+    }
+}
+
+pub fn run_match_report<P, F, S, W, G>(
+    settings: &MatchSettings,
+    names: (&str, &str),
+    first: F,
+    second: S,
+    output: &mut W,
+    mut games_output: Option<G>,
+) -> io::Result<MatchScore>
+where
+    P: Player + Clone + Send + 'static,
+    F: Fn() -> P + Sync,
+    S: Fn() -> P + Sync,
+    W: Write + Send,
+    G: Write + Send,
+{
+    let (first_name, second_name) = names;
+    let result = run_match(settings, first, second, |game, played, first_is_black, result| {
+        let (black, white) = if first_is_black { (first_name, second_name) } else { (second_name, first_name) };
+        let end = match played.end {
+            GameEnd::Finished => "",
+            GameEnd::Adjudicated => " (adjudicated)",
+            GameEnd::Forfeit(_) => " (forfeit)",
+            GameEnd::Timeout(_) => " (time)",
+        };
+        writeln!(output, "Game {}: {} - {} {:+}{}, score {}", game + 1, black, white, played.discs, end, result)
+            .expect("could not write the game");
+
+        if let Some(file) = games_output.as_mut() {
+            let mut ggf = GgfGame::new(&played.start, black, white);
+            for (board, ply) in &played.moves {
+                ggf.push_ply(board, *ply);
+            }
+            match &played.result {
+                Some(result) => ggf.set_game_result(result),
+                None => ggf.set_result(played.discs, false),
+            }
+            writeln!(file, "{}", ggf).expect("could not write the game");
+        }
+    });
+
+    writeln!(output, "{} vs {}: {}", first_name, second_name, result)?;
+    if let Some((elo, low, high)) = elo_difference(&result) {
+        writeln!(output, "Elo difference: {:+.1} (95% between {:+.1} and {:+.1})", elo, low, high)?;
+    }
+    if let Some(sprt) = settings.sprt {
+        let (lower, upper) = sprt.bounds();
+        let decision = match sprt.decision(&result) {
+            Some(SprtDecision::AcceptH0) => "H0 accepted",
+            Some(SprtDecision::AcceptH1) => "H1 accepted",
+            None => "no decision",
+        };
+        writeln!(output, "SPRT elo0 {} elo1 {}: LLR {:.2} ({:.2}, {:.2}), {}", sprt.elo0, sprt.elo1, sprt.llr(&result), lower, upper, decision)?;
+    }
+    Ok(result)
+}
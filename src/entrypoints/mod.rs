@@ -1 +1,11 @@
+#[cfg(feature = "gui")]
 pub mod play;
+pub mod nboard;
+pub mod gtp;
+pub mod tui;
+pub mod analyse;
+pub mod solve_suite;
+pub mod matches;
+pub mod db;
+pub mod transcript;
+pub mod export_image;
@@ -0,0 +1,172 @@
+// NBoard protocol
+//
+// NBoard (and other Othello GUIs) talk to an engine through its stdin and stdout, one command
+// per line. The commands handled here are
+//   nboard <version>      -> set myname Revello
+//   set depth <n>         caps the search depth
+//   set game <ggf>        replaces the game with a GGF record
+//   set contempt <n>      ignored
+//   move <move>[/eval/time]
+//   go                    -> === <move>/<eval>/<time>
+//   hint <n>              -> search <move> <eval> 0 <depth>, for the n best moves
+//   learn                 -> learned
+//   ping <n>              -> pong <n>
+// Evaluations are in discs from the point of view of the side to move. Commands are handled
+// one after the other, so `pong` also tells the GUI that every command before it is done.
+
+use crate::ai::player::MinMaxPlayer;
+use crate::ai::evaluator::Evaluator;
+use crate::board::board::{Board, START_BOARD};
+use crate::formats::ggf::{parse_game, parse_move, apply_move};
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+pub const ENGINE_NAME: &str = "Revello";
+
+// hints search every move to the full depth, so they get a shallower search than `go`
+const MAX_HINT_DEPTH: u32 = 6;
+
+pub struct NBoardEngine<E: Evaluator + Clone> {
+    player: MinMaxPlayer<E>,
+    board: Board,
+    move_time: Duration,
+}
+
+impl<E: Evaluator + Clone> NBoardEngine<E> {
+    pub fn new(player: MinMaxPlayer<E>, move_time: Duration) -> Self {
+        NBoardEngine {
+            player: player.quiet(),
+            board: START_BOARD,
+            move_time: move_time,
+        }
+    }
+
+    pub fn handle<W: Write>(&mut self, line: &str, out: &mut W) -> io::Result<()> {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+
+        match command {
+            "" => {},
+            "nboard" => writeln!(out, "set myname {}", ENGINE_NAME)?,
+            "set" => {
+                let (option, value) = args.split_once(' ').unwrap_or((args, ""));
+                match option {
+                    "depth" => match value.trim().parse::<u32>() {
+                        Ok(depth) if depth > 0 => {
+                            let mut strength = self.player.strength();
+                            strength.max_depth = depth;
+                            self.player = self.player.clone().with_strength(strength);
+                        }
+                        _ => eprintln!("invalid depth: {}", value),
+                    },
+                    "game" => match parse_game(value).and_then(|game| game.final_board()) {
+                        Ok(board) => self.board = board,
+                        Err(e) => eprintln!("invalid game: {}", e),
+                    },
+                    "contempt" => {},
+                    _ => eprintln!("unknown option: {}", option),
+                }
+            }
+            "move" => self.play_move(args),
+            "go" => self.go(out)?,
+            "hint" => self.hint(args, out)?,
+            "learn" => writeln!(out, "learned")?,
+            "ping" => writeln!(out, "pong {}", args)?,
+            _ => eprintln!("unknown command: {}", line),
+        }
+        out.flush()
+    }
+
+    fn play_move(&mut self, text: &str) {
+        let turn = match self.board.turn {
+            Some(turn) => turn,
+            None => return eprintln!("move after the end of the game: {}", text),
+        };
+        // passes are played automatically
+        let result = parse_move(turn, text).and_then(|ggf_move| match ggf_move.ply {
+            None => Ok(self.board.clone()),
+            Some(_) => apply_move(&self.board, &ggf_move),
+        });
+        match result {
+            Ok(board) => self.board = board,
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    fn go<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        let turn = match self.board.turn {
+            Some(turn) => turn,
+            None => return writeln!(out, "=== PA"),
+        };
+        writeln!(out, "status Thinking")?;
+        out.flush()?;
+
+        let info = self.player.search(&self.board, self.move_time);
+        let eval = info.eval.map_or(0.0, |eval| eval.for_player(turn).discs());
+        writeln!(out, "nodestats {} {:.2}", info.stats.nodes, info.time.as_secs_f32())?;
+        writeln!(out, "=== {}/{:.2}/{:.2}", info.ply.to_notation().to_uppercase(), eval, info.time.as_secs_f32())?;
+        writeln!(out, "status")
+    }
+
+    fn hint<W: Write>(&mut self, args: &str, out: &mut W) -> io::Result<()> {
+        let count = match args.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => {
+                eprintln!("invalid number of hints: {}", args);
+                return Ok(());
+            }
+        };
+        if self.board.turn.is_none() {
+            return Ok(());
+        }
+        let depth = self.player.strength().max_depth.min(MAX_HINT_DEPTH);
+        writeln!(out, "status Analysing")?;
+        out.flush()?;
+
+        for (ply, eval) in self.player.analyse_moves(&self.board, depth).into_iter().take(count) {
+            writeln!(out, "search {} {:.2} 0 {}", ply.to_notation().to_uppercase(), eval.discs(), depth)?;
+        }
+        writeln!(out, "status")
+    }
+}
+
+pub fn run_nboard<E, R, W>(player: MinMaxPlayer<E>, move_time: Duration, input: R, mut output: W) -> io::Result<()>
+where
+    E: Evaluator + Clone,
+    R: BufRead,
+    W: Write,
+{
+    // Answers commands until the input is closed
+    let mut engine = NBoardEngine::new(player, move_time);
+    for line in input.lines() {
+        engine.handle(&line?, &mut output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(engine: &mut NBoardEngine<crate::ai::evaluator::AnyEvaluator>, line: &str) -> String {
+        let mut out = Vec::new();
+        engine.handle(line, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_moves_and_game() {
+        let mut engine = NBoardEngine::new(MinMaxPlayer::new(), Duration::from_millis(100));
+        assert_eq!(answer(&mut engine, "move F5"), "");
+        assert_eq!(engine.board.count_pieces(), 5);
+        // illegal moves are ignored
+        answer(&mut engine, "move A1");
+        assert_eq!(engine.board.count_pieces(), 5);
+
+        answer(&mut engine, "set game (;GM[Othello]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[F5]W[F6];)");
+        assert_eq!(engine.board.count_pieces(), 6);
+        assert_eq!(answer(&mut engine, "ping 3"), "pong 3\n");
+    }
+}
@@ -1,112 +1,106 @@
-use crate::graphics::graphics::{draw_board, draw_timers, draw_side_pannel};
-use crate::board::board::{START_BOARD, Player, play, Ply};
+use crate::graphics::graphics::{draw_board, draw_timers, draw_side_pannel, draw_playable, detect_ply, draw_result, ResultAction};
+use crate::board::board::{Board, Player};
 use crate::ai::player::{Player as AiPlayer};
-use macroquad::prelude::next_frame;
-
-use std::thread;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::{Ordering, AtomicBool};
-
-use std::time::{Duration, Instant};
+use crate::game::session::{GameSession, GameEvent, TimeoutPolicy};
+use crate::game::clock::TimeControl;
+use crate::formats::ggf::GgfGame;
+use crate::game::openings::opening_names;
+use macroquad::prelude::{next_frame, is_key_pressed, KeyCode};
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn save_game<P: AiPlayer + Clone + Send + 'static>(session: &GameSession<P>, black_name: &str, white_name: &str) -> std::io::Result<String> {
+    // writes the game so far to a GGF file named after the current time, and returns its name
+    let start = session.history().first().map_or(session.board(), |(board, _)| board);
+    let mut game = GgfGame::from_history(start, session.history(), black_name, white_name);
+    if let Some(result) = session.result() {
+        game.set_game_result(&result);
+    }
+    let now = SystemTime::now();
+    game.set_date(now);
+    let path = format!("revello-{}.ggf", now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+    std::fs::write(&path, format!("{}\n", game))?;
+    Ok(path)
+}
 
-pub async fn player_vs_player<T, U>(
-    black_player: &mut T,
-    white_player: &mut U,
-    black_time: Duration,
-    white_time: Duration,
+#[allow(clippy::too_many_arguments)]
+pub async fn player_vs_player<P>(
+    black_player: P,
+    white_player: P,
+    black_name: String,
+    white_name: String,
+    start: Board,
+    black_control: TimeControl,
+    white_control: TimeControl,
+    timeout_policy: TimeoutPolicy,
     )
 where
-    T: AiPlayer + Clone + std::marker::Send + 'static,
-    U: AiPlayer + Clone + std::marker::Send + 'static,
+    P: AiPlayer + Clone + std::marker::Send + 'static,
 {
-    let mut board = START_BOARD.clone();
-
-    let black_total_time = black_time.clone();
-    let white_total_time = white_time.clone();
-
-    let mut black_time = black_total_time.clone();
-    let mut white_time = white_total_time.clone();
-
-    let start_time = Instant::now(); // start timer
-
-    let black_move: Arc<Mutex<Option<Ply>>> = Arc::new(Mutex::new(None));
-    let white_move: Arc<Mutex<Option<Ply>>> = Arc::new(Mutex::new(None));
-
-    let ai_thinking = Arc::new(AtomicBool::new(false));
+    // games are played from `start` until the players quit from the result screen
+    let (mut black_player, mut white_player) = (black_player, white_player);
+    let (mut black_name, mut white_name) = (black_name, white_name);
+    let (mut black_control, mut white_control) = (black_control, white_control);
+    let mut session = GameSession::new(black_player.clone(), white_player.clone(), black_control, white_control)
+        .with_timeout_policy(timeout_policy)
+        .with_position(&start);
 
     loop {
-        // update
-        black_player.update(&board);
-        white_player.update(&board);
-        // draw
-        next_frame().await;
-        draw_board(&board);
-        draw_side_pannel(&board);
-        draw_timers(&black_time, &white_time, board.turn == Some(Player::Black));
-
-        // Time keeping
-        if board.turn == Some(Player::Black) {
-            let elapsed = Instant::now() - start_time;
-            if let Some(remaining) = (black_total_time + white_total_time).checked_sub(elapsed + white_time) {
-                black_time = remaining;
-            } else {
-                black_time = Duration::from_secs(0);
-                continue;
-            }
-        } else if board.turn == Some(Player::White) {
-            let elapsed = Instant::now() - start_time;
-            if let Some(remaining) = (black_total_time + white_total_time).checked_sub(elapsed + black_time) {
-                white_time = remaining;
-            } else {
-                white_time = Duration::from_secs(0);
-                continue;
+        // update, P pauses and resumes the clock
+        if is_key_pressed(KeyCode::P) && !session.is_over() {
+            if session.is_paused() { session.resume() } else { session.pause() }
+        }
+        // G saves the game
+        if is_key_pressed(KeyCode::G) {
+            match save_game(&session, &black_name, &white_name) {
+                Ok(path) => println!("Game saved to {}", path),
+                Err(e) => eprintln!("Could not save the game: {}", e),
             }
         }
-
-        // move gen
-        if board.turn == Some(Player::Black)
-            && black_move.lock().unwrap().is_none()
-            && !ai_thinking.load(Ordering::SeqCst)
-        {
-            let black_move_clone = black_move.clone();
-            let board_clone = board.clone();
-            let black_player_clone = black_player.clone();
-            let ai_thinking_clone = ai_thinking.clone();
-
-            ai_thinking.store(true, Ordering::SeqCst);
-
-            thread::spawn( move || {
-                let ply = black_player_clone.generate_ply(&board_clone, black_time);
-                *black_move_clone.lock().unwrap() = Some(ply);
-                ai_thinking_clone.store(false, Ordering::SeqCst);
-                    });
+        for event in session.tick() {
+            match event {
+                GameEvent::TimedOut(Player::Black) => println!("Black ran out of time"),
+                GameEvent::TimedOut(Player::White) => println!("White ran out of time"),
+                GameEvent::Forfeited(Player::Black) => println!("Black forfeits"),
+                GameEvent::Forfeited(Player::White) => println!("White forfeits"),
+                GameEvent::GameOver => println!("{}", session.result().expect("the game is over")),
+                _ => {},
+            }
         }
 
-        if board.turn == Some(Player::White)
-            && white_move.lock().unwrap().is_none()
-            && !ai_thinking.load(Ordering::SeqCst)
-        {
-            let white_move_clone = white_move.clone();
-            let board_clone = board.clone();
-            let white_player_clone = white_player.clone();
-            let ai_thinking_clone = ai_thinking.clone();
-
-            ai_thinking.store(true, Ordering::SeqCst);
+        // draw
+        next_frame().await;
+        let board = session.board().clone();
+        draw_board(&board);
+        let boards = session.history().iter().map(|(board, _)| board).chain(std::iter::once(&board));
+        draw_side_pannel(&board, opening_names().name_of_game(boards));
+        draw_timers(&session.time_left(Player::Black), &session.time_left(Player::White), board.turn == Some(Player::Black));
 
-            thread::spawn( move || {
-                let ply = white_player_clone.generate_ply(&board_clone, white_time);
-                *white_move_clone.lock().unwrap() = Some(ply);
-                ai_thinking_clone.store(false, Ordering::SeqCst);
-                    });
+        // a human to move picks a move with the mouse
+        if session.is_paused() {
+            continue;
         }
-
-        if let Some(ply) = black_move.lock().unwrap().take() {
-            board = play(&board, ply);
+        if let Some(input) = session.current_player().and_then(|player| player.move_input()) {
+            draw_playable(&board);
+            if let Some(ply) = detect_ply() {
+                input.select(ply);
+            }
         }
 
-        if let Some(ply) = white_move.lock().unwrap().take() {
-            board = play(&board, ply);
+        if let Some(result) = session.result() {
+            match draw_result(&result) {
+                Some(ResultAction::Rematch) => {},
+                Some(ResultAction::SwapColours) => {
+                    std::mem::swap(&mut black_player, &mut white_player);
+                    std::mem::swap(&mut black_name, &mut white_name);
+                    std::mem::swap(&mut black_control, &mut white_control);
+                }
+                Some(ResultAction::Quit) => return,
+                None => continue,
+            }
+            session = GameSession::new(black_player.clone(), white_player.clone(), black_control, white_control)
+                .with_timeout_policy(timeout_policy)
+                .with_position(&start);
         }
-
     }
 }
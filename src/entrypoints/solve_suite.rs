@@ -0,0 +1,76 @@
+// Endgame test suites
+//
+// Solves every position of an OBF file exactly and compares the engine's move and score with
+// the ones the file gives, one line per position, followed by the totals. A position is
+// solved correctly when the score matches the best known score and the move is one of the
+// known moves reaching it. Positions without known moves are only solved.
+
+use crate::ai::tournament::solve_position;
+use crate::board::board::Player;
+use crate::formats::obf::{ObfPosition, parse_obf};
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SuiteResult {
+    pub positions: u32,
+    // positions with known moves, and those solved correctly
+    pub checked: u32,
+    pub correct: u32,
+    pub nodes: u64,
+    pub time: Duration,
+}
+
+pub fn run_suite<W: Write>(positions: &[ObfPosition], output: &mut W) -> io::Result<SuiteResult> {
+    let mut result = SuiteResult::default();
+    for (i, position) in positions.iter().enumerate() {
+        let board = &position.board;
+        let turn = board.turn.expect("OBF positions have a side to move");
+        let solution = solve_position(board);
+        let ply = solution.ply.expect("a position with a move to play has a best move");
+        let score = if turn == Player::Black { solution.discs } else { -solution.discs };
+
+        write!(
+            output,
+            "{:>4}  {:>2} empties  {} {:+3}",
+            i + 1, 64 - board.count_pieces(), ply.to_notation().to_uppercase(), score,
+        )?;
+        if let Some(best) = position.best_score() {
+            let expected = position.best_moves().iter()
+                .map(|ply| ply.to_notation().to_uppercase())
+                .collect::<Vec<String>>()
+                .join(" ");
+            let status = if score != best {
+                "wrong score"
+            } else if !position.best_moves().contains(&ply) {
+                "wrong move"
+            } else {
+                result.correct += 1;
+                "ok"
+            };
+            write!(output, "  expected {} {:+3}  {:<11}", expected, best, status)?;
+            result.checked += 1;
+        }
+        writeln!(output, "  {:>12} nodes  {:>8.3}s", solution.stats.nodes, solution.time.as_secs_f64())?;
+
+        result.positions += 1;
+        result.nodes += solution.stats.nodes;
+        result.time += solution.time;
+    }
+
+    let nodes_per_second = result.nodes as f64 / result.time.as_secs_f64().max(1e-9);
+    writeln!(
+        output,
+        "{}/{} correct, {} positions, {} nodes in {:.3}s ({:.0} nodes/s)",
+        result.correct, result.checked, result.positions, result.nodes, result.time.as_secs_f64(), nodes_per_second,
+    )?;
+    Ok(result)
+}
+
+pub fn run_suite_file<W: Write>(path: &str, output: &mut W) -> Result<SuiteResult, String> {
+    // the suite of the positions of an OBF file
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let positions = parse_obf(&text).map_err(|e| format!("{}: {}", path, e))?;
+    run_suite(&positions, output).map_err(|e| e.to_string())
+}
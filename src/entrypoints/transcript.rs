@@ -0,0 +1,43 @@
+// Scoresheets
+//
+// `transcript import` turns a grid of move numbers into a GGF record, replaying it to check
+// the numbering, and `transcript export` prints the games of a GGF file as grids.
+
+use crate::board::board::{Board, play};
+use crate::formats::ggf::{GgfGame, parse_games};
+use crate::formats::transcript::Transcript;
+use crate::game::result::GameResult;
+
+use std::io::Write;
+
+pub fn transcript_game(transcript: &Transcript, black: &str, white: &str) -> Result<GgfGame, String> {
+    let history = transcript.replay()?;
+    let mut game = GgfGame::from_history(&transcript.start, &history, black, white);
+    // sheets of unfinished games have no result
+    let board = history.last().map_or(transcript.start.clone(), |(board, ply)| play(board, *ply));
+    if board.turn.is_none() {
+        game.set_game_result(&GameResult::finished(&board));
+    }
+    Ok(game)
+}
+
+pub fn import_transcript(path: &str, start: &Board, black: &str, white: &str) -> Result<GgfGame, String> {
+    // the game of the sheet in the file, played from `start`
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let transcript: Transcript = text.parse().map_err(|e| format!("{}: {}", path, e))?;
+    transcript_game(&transcript.with_start(start), black, white).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn export_transcripts<W: Write>(path: &str, output: &mut W) -> Result<(), String> {
+    // the sheet of every game of a GGF file, after a line naming its players
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let games = parse_games(&text).map_err(|e| format!("{}: {}", path, e))?;
+    for (i, game) in games.iter().enumerate() {
+        let positions = game.positions().map_err(|e| format!("{}: game {}: {}", path, i + 1, e))?;
+        let moves: Vec<_> = positions.iter().filter_map(|(_, ggf_move)| ggf_move.ply).collect();
+        writeln!(output, "Game {}: {} vs {}", i + 1, game.tag("PB").unwrap_or("?"), game.tag("PW").unwrap_or("?"))
+            .and_then(|_| writeln!(output, "{}", Transcript::from_moves(&game.start, &moves)))
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,169 @@
+// Text mode front end
+//
+// Plays a game in the terminal, without a window. The board is printed after every move with
+// its coordinates, the legal moves of the side to move are marked with `*`, and the clocks are
+// shown under it. Humans type their moves (`f5`); `quit` gives up the game. Engines are the
+// same `Player`s as in the window and print their search information as they go. The game
+// itself is a `GameSession`.
+
+use crate::ai::player::Player as AiPlayer;
+use crate::board::board::{Board, Ply, Player, possible_plys};
+use crate::game::session::{GameSession, GameEvent, SessionState, TimeoutPolicy};
+use crate::game::result::GameResult;
+use crate::game::clock::{TimeControl, format_clock};
+
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+fn color_name(player: Player) -> &'static str {
+    match player {
+        Player::Black => "Black",
+        Player::White => "White",
+    }
+}
+
+pub fn render(board: &Board, black_time: Duration, white_time: Duration) -> String {
+    let legal = if board.turn.is_some() { u64::from(possible_plys(board)) } else { 0 };
+    let mut text = String::from("    a b c d e f g h\n");
+    for row in 0..8 {
+        text += &format!("  {}", row + 1);
+        for col in 0..8 {
+            let mask = 1u64 << (row * 8 + col);
+            let square = if board.black & mask != 0 {
+                'X'
+            } else if board.white & mask != 0 {
+                'O'
+            } else if legal & mask != 0 {
+                '*'
+            } else {
+                '.'
+            };
+            text.push(' ');
+            text.push(square);
+        }
+        text.push('\n');
+    }
+    text += &format!(
+        "  X {:>2}  {}{}\n  O {:>2}  {}{}\n",
+        board.count_black(), format_clock(black_time), if board.turn == Some(Player::Black) { "  <- to move" } else { "" },
+        board.count_white(), format_clock(white_time), if board.turn == Some(Player::White) { "  <- to move" } else { "" },
+    );
+    text
+}
+
+fn read_move<R: BufRead, W: Write>(board: &Board, input: &mut R, output: &mut W) -> io::Result<Option<Ply>> {
+    // Asks until a legal move is typed. None if the player quits or the input ends.
+    let turn = board.turn.expect("no move to read");
+    loop {
+        write!(output, "{} to move: ", color_name(turn))?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line == "quit" || line == "q" {
+            return Ok(None);
+        }
+        match Ply::from_notation(line) {
+            Some(ply) if ply.is_in(possible_plys(board)) => return Ok(Some(ply)),
+            Some(_) => writeln!(output, "{} is not a legal move", line)?,
+            None => writeln!(output, "type a move like f5, or quit")?,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_tui<P, R, W>(
+    black: P,
+    white: P,
+    start: &Board,
+    black_control: TimeControl,
+    white_control: TimeControl,
+    timeout_policy: TimeoutPolicy,
+    mut input: R,
+    mut output: W,
+) -> io::Result<GameResult>
+where
+    P: AiPlayer + Clone + Send + 'static,
+    R: BufRead,
+    W: Write,
+{
+    // Plays a game from `start` and returns its result.
+    // Players that take their moves from the user interface read them from `input`.
+    let mut session = GameSession::new(black, white, black_control, white_control)
+        .with_timeout_policy(timeout_policy)
+        .with_position(start);
+    write!(output, "\n{}", render(session.board(), black_control.base, white_control.base))?;
+
+    while !session.is_over() {
+        let turn = session.board().turn.expect("the game is not over");
+        let mut events = Vec::new();
+        if let Some(move_input) = session.current_player().and_then(|player| player.move_input()) {
+            match read_move(session.board(), &mut input, &mut output)? {
+                Some(ply) => move_input.select(ply),
+                None => events = session.resign(turn),
+            }
+        }
+        output.flush()?;
+        if events.is_empty() {
+            events = session.advance(Duration::MAX);
+        }
+
+        for event in events {
+            match event {
+                GameEvent::Moved { player, ply } => {
+                    writeln!(output, "{} plays {}", color_name(player), ply.to_notation())?;
+                }
+                GameEvent::Passed(player) => writeln!(output, "{} has to pass", color_name(player))?,
+                GameEvent::TimedOut(player) => writeln!(output, "{} ran out of time", color_name(player))?,
+                GameEvent::Forfeited(player) => writeln!(output, "{} gives up", color_name(player))?,
+                GameEvent::GameOver => {},
+            }
+        }
+        if session.state() == SessionState::Playing || session.state() == SessionState::Finished {
+            let board = session.board();
+            write!(output, "\n{}", render(board, session.time_left(Player::Black), session.time_left(Player::White)))?;
+        }
+    }
+
+    let result = session.result().expect("the game is over");
+    writeln!(output, "{}", result)?;
+    output.flush()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::player::{AnyPlayer, HumanPlayer, GreedyPlayer};
+    use crate::board::board::START_BOARD;
+
+    #[test]
+    fn test_render() {
+        let text = render(&START_BOARD, Duration::from_secs(300), Duration::from_secs(65));
+        assert!(text.contains("  4 . . * O X . . .\n"));
+        assert!(text.contains("  3 . . . * . . . .\n"));
+        assert!(text.contains("X  2  5:00  <- to move\n"));
+        assert!(text.contains("O  2  1:05\n"));
+    }
+
+    #[test]
+    fn test_human_moves() {
+        let input = "e9\na1\nf5\nquit\n";
+        let mut output = Vec::new();
+        let human = AnyPlayer::Human(HumanPlayer::new());
+        let greedy = AnyPlayer::Greedy(GreedyPlayer);
+        let control = TimeControl::new(Duration::from_secs(60));
+        let result = run_tui(human, greedy, &START_BOARD, control, control, TimeoutPolicy::Lose, input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("type a move like f5"));
+        assert!(output.contains("a1 is not a legal move"));
+        assert!(output.contains("Black plays f5"));
+        assert!(output.contains("White plays "));
+        assert!(output.contains("Black gives up"));
+        assert_eq!(result.winner, Some(Player::White));
+        assert!(output.ends_with("White wins by resignation 61-3\n"));
+    }
+}
@@ -0,0 +1,286 @@
+// Board diagrams as SVG and PNG images
+//
+// Draws a board without a window, for newsletters and bug reports: the grid with its a-h and
+// 1-8 coordinates, the discs, and optionally dots on the legal moves, a ring around the last
+// move and the move numbers of a game written on its discs. The picture is first laid out as a
+// list of shapes, which are then written as SVG or rasterised into a PNG by tiny-skia. Tiny-skia
+// draws no text, so the PNG writes the coordinates and numbers with a small built-in pixel font.
+
+use crate::board::board::{Board, Ply, Player, possible_plys, play};
+
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+pub const DEFAULT_SQUARE_SIZE: u32 = 60;
+
+type Rgb = (u8, u8, u8);
+
+// the board and disc colours of the game window, so a diagram looks like the game
+const BACKGROUND_COLOR: Rgb = (255, 255, 255);
+const BOARD_COLOR: Rgb = (89, 191, 89);
+const GRID_COLOR: Rgb = (0, 0, 0);
+const BLACK_COLOR: Rgb = (64, 64, 64);
+const WHITE_COLOR: Rgb = (235, 235, 224);
+// the marks only diagrams draw
+const LEGAL_MOVE_COLOR: Rgb = (40, 110, 40);
+const LAST_MOVE_COLOR: Rgb = (220, 40, 40);
+const COORDINATE_COLOR: Rgb = (0, 0, 0);
+
+pub struct Diagram {
+    pub board: Board,
+    pub legal_moves: bool,
+    pub last_move: Option<Ply>,
+    // the move number of every square, 0 for none, like a transcript
+    pub numbers: [u8; 64],
+    // pixels
+    pub square_size: u32,
+}
+
+enum Shape {
+    Rect { x: f32, y: f32, width: f32, height: f32, fill: Rgb },
+    Circle { x: f32, y: f32, radius: f32, fill: Option<Rgb>, stroke: Option<(Rgb, f32)> },
+    // centred on x, y; the height is that of a digit
+    Text { x: f32, y: f32, height: f32, text: String, fill: Rgb },
+}
+
+impl Diagram {
+    pub fn new(board: &Board) -> Self {
+        Diagram { board: board.clone(), legal_moves: false, last_move: None, numbers: [0; 64], square_size: DEFAULT_SQUARE_SIZE }
+    }
+
+    pub fn from_game(start: &Board, moves: &[Ply]) -> Self {
+        // the board after the moves, with the last one marked
+        let board = moves.iter().fold(start.clone(), |board, ply| play(&board, *ply));
+        let mut diagram = Diagram::new(&board);
+        diagram.last_move = moves.last().copied();
+        diagram
+    }
+
+    pub fn with_legal_moves(mut self) -> Self {
+        self.legal_moves = true;
+        self
+    }
+
+    pub fn with_numbers(mut self, numbers: [u8; 64]) -> Self {
+        self.numbers = numbers;
+        self
+    }
+
+    pub fn with_square_size(mut self, square_size: u32) -> Self {
+        self.square_size = square_size;
+        self
+    }
+
+    pub fn size(&self) -> u32 {
+        // half a square of margin for the coordinates on each side
+        self.square_size * 9
+    }
+
+    fn shapes(&self) -> Vec<Shape> {
+        let square = self.square_size as f32;
+        let margin = square / 2.0;
+        let center = |index: usize| (margin + ((index % 8) as f32 + 0.5) * square, margin + ((index / 8) as f32 + 0.5) * square);
+        let line = (square / 30.0).max(1.0);
+
+        let mut shapes = vec![
+            Shape::Rect { x: 0.0, y: 0.0, width: self.size() as f32, height: self.size() as f32, fill: BACKGROUND_COLOR },
+            Shape::Rect { x: margin, y: margin, width: 8.0 * square, height: 8.0 * square, fill: BOARD_COLOR },
+        ];
+        for i in 0..9 {
+            let offset = margin + i as f32 * square - line / 2.0;
+            shapes.push(Shape::Rect { x: offset, y: margin - line / 2.0, width: line, height: 8.0 * square + line, fill: GRID_COLOR });
+            shapes.push(Shape::Rect { x: margin - line / 2.0, y: offset, width: 8.0 * square + line, height: line, fill: GRID_COLOR });
+        }
+        for i in 0..8 {
+            let along = margin + (i as f32 + 0.5) * square;
+            let height = square / 4.0;
+            for across in [margin / 2.0, self.size() as f32 - margin / 2.0] {
+                let column = ((b'A' + i as u8) as char).to_string();
+                shapes.push(Shape::Text { x: along, y: across, height: height, text: column, fill: COORDINATE_COLOR });
+                shapes.push(Shape::Text { x: across, y: along, height: height, text: (i + 1).to_string(), fill: COORDINATE_COLOR });
+            }
+        }
+
+        for index in 0..64 {
+            let mask = 1u64 << index;
+            let (x, y) = center(index);
+            let (fill, text) = if self.board.black & mask != 0 {
+                (BLACK_COLOR, WHITE_COLOR)
+            } else if self.board.white & mask != 0 {
+                (WHITE_COLOR, BLACK_COLOR)
+            } else {
+                continue;
+            };
+            shapes.push(Shape::Circle { x: x, y: y, radius: square * 0.4, fill: Some(fill), stroke: Some((GRID_COLOR, line)) });
+            if self.numbers[index] != 0 {
+                shapes.push(Shape::Text { x: x, y: y, height: square / 4.0, text: self.numbers[index].to_string(), fill: text });
+            }
+        }
+        if let Some(ply) = self.last_move {
+            let (row, col) = ply.to_row_col();
+            let (x, y) = center(row * 8 + col);
+            shapes.push(Shape::Circle { x: x, y: y, radius: square * 0.44, fill: None, stroke: Some((LAST_MOVE_COLOR, 3.0 * line)) });
+        }
+        if self.legal_moves && self.board.turn.is_some() {
+            for ply in possible_plys(&self.board).to_vec_ply() {
+                let (row, col) = ply.to_row_col();
+                let (x, y) = center(row * 8 + col);
+                shapes.push(Shape::Circle { x: x, y: y, radius: square * 0.1, fill: Some(LEGAL_MOVE_COLOR), stroke: None });
+            }
+        }
+        shapes
+    }
+
+    pub fn to_svg(&self) -> String {
+        let size = self.size();
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" viewBox=\"0 0 {0} {0}\">\n", size);
+        let side = match self.board.turn {
+            Some(Player::Black) => "black",
+            Some(Player::White) => "white",
+            None => "nobody",
+        };
+        svg += &format!("<title>{} to move</title>\n", side);
+        for shape in self.shapes() {
+            svg += &match shape {
+                Shape::Rect { x, y, width, height, fill } => {
+                    format!("<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n", x, y, width, height, hex(fill))
+                }
+                Shape::Circle { x, y, radius, fill, stroke } => {
+                    let fill = fill.map_or("none".to_string(), hex);
+                    let stroke = stroke.map_or(String::new(), |(color, width)| format!(" stroke=\"{}\" stroke-width=\"{}\"", hex(color), width));
+                    format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"{}/>\n", x, y, radius, fill, stroke)
+                }
+                Shape::Text { x, y, height, text, fill } => {
+                    // digits are about 0.7 em high
+                    format!(
+                        "<text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\" fill=\"{}\">{}</text>\n",
+                        x, y, height / 0.7, hex(fill), text,
+                    )
+                }
+            };
+        }
+        svg += "</svg>\n";
+        svg
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>, String> {
+        let mut pixmap = Pixmap::new(self.size(), self.size()).ok_or("the image is too large")?;
+        for shape in self.shapes() {
+            match shape {
+                Shape::Rect { x, y, width, height, fill } => fill_rect(&mut pixmap, x, y, width, height, fill),
+                Shape::Circle { x, y, radius, fill, stroke } => {
+                    let path = PathBuilder::from_circle(x, y, radius).ok_or("invalid circle")?;
+                    if let Some(color) = fill {
+                        pixmap.fill_path(&path, &paint(color), FillRule::Winding, Transform::identity(), None);
+                    }
+                    if let Some((color, width)) = stroke {
+                        let stroke = Stroke { width: width, ..Stroke::default() };
+                        pixmap.stroke_path(&path, &paint(color), &stroke, Transform::identity(), None);
+                    }
+                }
+                Shape::Text { x, y, height, text, fill } => draw_text(&mut pixmap, x, y, height, &text, fill),
+            }
+        }
+        pixmap.encode_png().map_err(|e| e.to_string())
+    }
+}
+
+fn hex((r, g, b): Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn paint((r, g, b): Rgb) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, 255);
+    paint.anti_alias = true;
+    paint
+}
+
+fn fill_rect(pixmap: &mut Pixmap, x: f32, y: f32, width: f32, height: f32, color: Rgb) {
+    // straight edges need no anti-aliasing, which also trips tiny-skia on thin rectangles
+    let mut paint = paint(color);
+    paint.anti_alias = false;
+    if let Some(rect) = Rect::from_xywh(x, y, width, height) {
+        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+    }
+}
+
+// 3x5 glyphs, a row per 3 bits from the top, the leftmost pixel in the high bit
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+fn glyph(c: char) -> Option<[u8; GLYPH_HEIGHT]> {
+    Some(match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        _ => return None,
+    })
+}
+
+fn draw_text(pixmap: &mut Pixmap, x: f32, y: f32, height: f32, text: &str, color: Rgb) {
+    // whole pixels of the font, so that the glyphs stay sharp
+    let pixel = (height / GLYPH_HEIGHT as f32).round().max(1.0);
+    let count = text.chars().count();
+    let width = (count * (GLYPH_WIDTH + 1) - 1) as f32 * pixel;
+    let left = (x - width / 2.0).round();
+    let top = (y - GLYPH_HEIGHT as f32 * pixel / 2.0).round();
+    for (i, c) in text.chars().enumerate() {
+        let Some(rows) = glyph(c) else {
+            continue;
+        };
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    let x = left + ((i * (GLYPH_WIDTH + 1) + col) as f32) * pixel;
+                    fill_rect(pixmap, x, top + row as f32 * pixel, pixel, pixel, color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::{START_BOARD, parse_moves};
+    use crate::formats::transcript::Transcript;
+
+    #[test]
+    fn test_diagram() {
+        let moves = parse_moves(&START_BOARD, "f5d6c3").unwrap();
+        let diagram = Diagram::from_game(&START_BOARD, &moves)
+            .with_legal_moves()
+            .with_numbers(Transcript::from_moves(&START_BOARD, &moves).numbers);
+        assert_eq!(diagram.size(), 540);
+
+        let svg = diagram.to_svg();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"540\""));
+        assert!(svg.contains("<title>white to move</title>"));
+        // 7 discs, the last move ring and a dot on each of white's 4 moves
+        assert_eq!(svg.matches("<circle").count(), 7 + 1 + 4);
+        assert_eq!(svg.matches("fill=\"#dc2828\"").count() + svg.matches("stroke=\"#dc2828\"").count(), 1);
+        // 16 coordinates on each axis, and the 3 numbers
+        assert_eq!(svg.matches("<text").count(), 32 + 3);
+        assert!(svg.contains(">3</text>") && svg.contains(">H</text>"));
+
+        let png = diagram.with_square_size(20).to_png().unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        let image = Pixmap::decode_png(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (180, 180));
+    }
+}
@@ -0,0 +1,421 @@
+// GGF (Generic Game Format) game records, as used by NBoard and GGS.
+//
+// A game looks like
+//   (;GM[Othello]PC[NBoard]PB[black]PW[white]RE[?]TY[8]
+//     BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]
+//     B[F5]W[F6]B[E6//1.2];)
+// BO is the start position, row by row from the top, with `*` black, `O` white and `-` empty,
+// followed by the side to move. B[..] and W[..] are the moves, `PA` is a pass. A move may be
+// followed by `/eval/time`, the score for the player that moved and the seconds it took.
+// Archives hold one game after the other, usually one per line.
+
+use crate::board::board::{Board, Ply, Player, possible_plys, play};
+use crate::game::result::{GameResult, ResultReason};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, PartialEq)]
+pub struct GgfMove {
+    pub player: Player,
+    // None is a pass
+    pub ply: Option<Ply>,
+    pub eval: Option<f32>,
+    // seconds
+    pub time: Option<f32>,
+}
+
+impl GgfMove {
+    pub fn new(player: Player, ply: Option<Ply>) -> Self {
+        GgfMove {
+            player: player,
+            ply: ply,
+            eval: None,
+            time: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GgfGame {
+    // every tag in the record, in order, moves included
+    pub tags: Vec<(String, String)>,
+    pub start: Board,
+    pub moves: Vec<GgfMove>,
+}
+
+fn parse_tags(text: &str) -> Result<Vec<(String, String)>, String> {
+    let text = text.trim();
+    let body = text.strip_prefix("(;")
+        .and_then(|body| body.strip_suffix(";)"))
+        .ok_or("a GGF game starts with \"(;\" and ends with \";)\"")?;
+
+    let mut tags = Vec::new();
+    let mut rest = body;
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok(tags);
+        }
+        let open = rest.find('[').ok_or(format!("tag without a value: {}", rest))?;
+        let close = rest[open..].find(']').ok_or(format!("unterminated tag: {}", rest))? + open;
+        let key = rest[..open].trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_uppercase()) {
+            return Err(format!("invalid tag name: {:?}", key));
+        }
+        tags.push((key.to_string(), rest[open + 1..close].to_string()));
+        rest = &rest[close + 1..];
+    }
+}
+
+pub fn parse_board(text: &str) -> Result<Board, String> {
+    // parses the value of a BO tag
+    let mut fields = text.split_whitespace();
+    if fields.next() != Some("8") {
+        return Err("only 8x8 boards are supported".to_string());
+    }
+    let squares: String = fields.collect();
+    if squares.chars().count() != 65 {
+        return Err(format!("a board has 64 squares and a side to move: {}", text));
+    }
+
+    let mut black = 0u64;
+    let mut white = 0u64;
+    for (index, c) in squares.chars().take(64).enumerate() {
+        match c {
+            '*' => black |= 1 << index,
+            'O' => white |= 1 << index,
+            '-' => {},
+            _ => return Err(format!("invalid square {:?}", c)),
+        }
+    }
+    let turn = match squares.chars().last() {
+        Some('*') => Player::Black,
+        Some('O') => Player::White,
+        c => return Err(format!("invalid side to move {:?}", c)),
+    };
+    let mut board = Board::new(black, white, Some(turn)).expect("a square can only hold one disc");
+    board.pass_if_needed();
+    Ok(board)
+}
+
+pub fn format_board(board: &Board) -> String {
+    // the value of a BO tag, the inverse of `parse_board`
+    let mut text = String::from("8");
+    for index in 0..64 {
+        if index % 8 == 0 {
+            text.push(' ');
+        }
+        let mask = 1u64 << index;
+        text.push(if board.black & mask != 0 { '*' } else if board.white & mask != 0 { 'O' } else { '-' });
+    }
+    // a finished game has no side to move, black is as good as any
+    text.push(' ');
+    text.push(if board.turn == Some(Player::White) { 'O' } else { '*' });
+    text
+}
+
+fn parse_number(text: &str) -> Result<Option<f32>, String> {
+    // an empty field is no value; times may also be written `m:ss`
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let mut value = 0.0;
+    for part in text.split(':') {
+        value = value * 60.0 + part.parse::<f32>().map_err(|_| format!("invalid number {:?}", text))?;
+    }
+    Ok(Some(value))
+}
+
+pub fn parse_move(player: Player, text: &str) -> Result<GgfMove, String> {
+    // parses the value of a B or W tag, `move/eval/time`
+    let mut fields = text.split('/');
+    let notation = fields.next().unwrap_or("").trim();
+    let ply = if notation.eq_ignore_ascii_case("pa") {
+        None
+    } else {
+        Some(Ply::from_notation(notation).ok_or(format!("invalid move {:?}", notation))?)
+    };
+    let eval = parse_number(fields.next().unwrap_or(""))?;
+    let time = parse_number(fields.next().unwrap_or(""))?;
+    Ok(GgfMove { player: player, ply: ply, eval: eval, time: time })
+}
+
+fn format_move(ggf_move: &GgfMove) -> String {
+    // the value of a B or W tag, the inverse of `parse_move`
+    let mut text = ggf_move.ply.map_or("PA".to_string(), |ply| ply.to_notation().to_uppercase());
+    if ggf_move.eval.is_some() || ggf_move.time.is_some() {
+        text += &format!("/{}", ggf_move.eval.map_or(String::new(), |eval| format!("{:.2}", eval)));
+    }
+    if let Some(time) = ggf_move.time {
+        text += &format!("/{:.2}", time);
+    }
+    text
+}
+
+pub fn apply_move(board: &Board, ggf_move: &GgfMove) -> Result<Board, String> {
+    // Plays a move on the board, checking that it is legal.
+    // `play` passes automatically, so a pass only has to be checked, not played.
+    match ggf_move.ply {
+        None => {
+            if board.turn == Some(ggf_move.player) {
+                return Err("pass while a move is possible".to_string());
+            }
+            Ok(board.clone())
+        }
+        Some(ply) => {
+            if board.turn != Some(ggf_move.player) || !ply.is_in(possible_plys(board)) {
+                return Err(format!("illegal move {}", ply.to_notation()));
+            }
+            Ok(play(board, ply))
+        }
+    }
+}
+
+pub fn parse_games(text: &str) -> Result<Vec<GgfGame>, String> {
+    // every game of an archive, in order
+    let mut games = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("(;") {
+        let end = rest[start..].find(";)").ok_or(format!("game {} is not terminated", games.len() + 1))? + start + 2;
+        games.push(parse_game(&rest[start..end]).map_err(|e| format!("game {}: {}", games.len() + 1, e))?);
+        rest = &rest[end..];
+    }
+    Ok(games)
+}
+
+pub fn parse_game(text: &str) -> Result<GgfGame, String> {
+    let tags = parse_tags(text)?;
+
+    let mut start = None;
+    let mut moves = Vec::new();
+    for (key, value) in &tags {
+        match key.as_str() {
+            "GM" if !value.eq_ignore_ascii_case("othello") => return Err(format!("not an Othello game: {}", value)),
+            "TY" if value.trim() != "8" => return Err(format!("unsupported game type: {}", value)),
+            "BO" => start = Some(parse_board(value)?),
+            "B" => moves.push(parse_move(Player::Black, value)?),
+            "W" => moves.push(parse_move(Player::White, value)?),
+            _ => {},
+        }
+    }
+
+    let game = GgfGame {
+        tags: tags,
+        start: start.ok_or("the game has no start position (BO)")?,
+        moves: moves,
+    };
+    game.final_board()?;
+    Ok(game)
+}
+
+impl GgfGame {
+    pub fn new(start: &Board, black: &str, white: &str) -> Self {
+        let tags = [
+            ("GM", "Othello".to_string()),
+            ("PC", "revello".to_string()),
+            ("PB", black.to_string()),
+            ("PW", white.to_string()),
+            ("RE", "?".to_string()),
+            ("TY", "8".to_string()),
+            ("BO", format_board(start)),
+        ];
+        GgfGame {
+            tags: tags.into_iter().map(|(key, value)| (key.to_string(), value)).collect(),
+            start: start.clone(),
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn from_history(start: &Board, history: &[(Board, Ply)], black: &str, white: &str) -> Self {
+        // a game from the positions and moves played, passes included
+        let mut game = GgfGame::new(start, black, white);
+        for (board, ply) in history {
+            game.push_ply(board, *ply);
+        }
+        game
+    }
+
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        self.tags.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, key: &str, value: &str) {
+        // replaces the value of a tag, or adds the tag in front of the moves
+        if let Some(tag) = self.tags.iter_mut().find(|(k, _)| k == key) {
+            tag.1 = value.to_string();
+            return;
+        }
+        let first_move = self.tags.iter().position(|(k, _)| k == "B" || k == "W").unwrap_or(self.tags.len());
+        self.tags.insert(first_move, (key.to_string(), value.to_string()));
+    }
+
+    pub fn set_result(&mut self, discs: i32, forfeit: bool) {
+        // the disc difference for black, `:r` marks a game that was given up
+        self.set_tag("RE", &format!("{:+}.000{}", discs, if forfeit { ":r" } else { "" }));
+    }
+
+    pub fn result(&self) -> Option<i32> {
+        // the disc difference for black written in RE, without the `:t` or `:r` mark
+        let value = self.tag("RE")?;
+        let discs = value.split(':').next()?.trim().parse::<f32>().ok()?;
+        Some(discs.round() as i32)
+    }
+
+    pub fn set_game_result(&mut self, result: &GameResult) {
+        // like `set_result`, with `:t` for a game lost on time
+        let reason = match result.reason {
+            ResultReason::Normal => "",
+            ResultReason::Timeout => ":t",
+            ResultReason::Resignation => ":r",
+        };
+        self.set_tag("RE", &format!("{:+}.000{}", result.margin(), reason));
+    }
+
+    pub fn set_date(&mut self, time: SystemTime) {
+        // `DT[2024.03.01_18:05:12.GMT]`
+        let seconds = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let (year, month, day) = civil_date((seconds / 86400) as i64);
+        let time_of_day = seconds % 86400;
+        let date = format!(
+            "{}.{:02}.{:02}_{:02}:{:02}:{:02}.GMT",
+            year, month, day, time_of_day / 3600, time_of_day % 3600 / 60, time_of_day % 60,
+        );
+        self.set_tag("DT", &date);
+    }
+
+    pub fn push_move(&mut self, ggf_move: GgfMove) {
+        let key = if ggf_move.player == Player::Black { "B" } else { "W" };
+        self.tags.push((key.to_string(), format_move(&ggf_move)));
+        self.moves.push(ggf_move);
+    }
+
+    pub fn push_ply(&mut self, board: &Board, ply: Ply) {
+        // Adds a move played on `board`, and the pass that follows if the opponent has no move
+        let player = board.turn.expect("a move after the end of the game");
+        self.push_move(GgfMove::new(player, Some(ply)));
+        if play(board, ply).turn == Some(player) {
+            let opponent = if player == Player::Black { Player::White } else { Player::Black };
+            self.push_move(GgfMove::new(opponent, None));
+        }
+    }
+
+    pub fn positions(&self) -> Result<Vec<(Board, GgfMove)>, String> {
+        // every move with the board it was played on
+        let mut board = self.start.clone();
+        let mut positions = Vec::new();
+        for (i, ggf_move) in self.moves.iter().enumerate() {
+            let next = apply_move(&board, ggf_move).map_err(|e| format!("move {}: {}", i + 1, e))?;
+            positions.push((board, ggf_move.clone()));
+            board = next;
+        }
+        Ok(positions)
+    }
+
+    pub fn final_board(&self) -> Result<Board, String> {
+        let mut board = self.start.clone();
+        for (i, ggf_move) in self.moves.iter().enumerate() {
+            board = apply_move(&board, ggf_move).map_err(|e| format!("move {}: {}", i + 1, e))?;
+        }
+        Ok(board)
+    }
+}
+
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    // the year, month and day `days` after 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl fmt::Display for GgfGame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(;")?;
+        for (key, value) in &self.tags {
+            write!(f, "{}[{}]", key, value)?;
+        }
+        write!(f, ";)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::START_BOARD;
+
+    const START: &str = "8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *";
+
+    #[test]
+    fn test_parse_game() {
+        let text = format!("(;GM[Othello]PC[NBoard]PB[a]PW[b]TY[8]BO[{}]B[F5//1.5]W[f6];)", START);
+        let game = parse_game(&text).unwrap();
+        assert!(game.start == START_BOARD);
+        assert_eq!(format_board(&game.start), START);
+        assert_eq!(game.moves.len(), 2);
+        let board = game.final_board().unwrap();
+        assert_eq!(board.count_pieces(), 6);
+        assert!(board.turn == Some(Player::Black));
+        assert_eq!(game.moves[0].time, Some(1.5));
+        assert_eq!(game.moves[0].eval, None);
+        assert_eq!(game.tag("PB"), Some("a"));
+
+        let positions = game.positions().unwrap();
+        assert!(positions[0].0 == START_BOARD);
+        assert_eq!(positions[1].1.ply, Ply::from_notation("f6"));
+    }
+
+    #[test]
+    fn test_parse_archive() {
+        let game = format!("(;GM[Othello]BO[{}]B[F5/-2.5/0:03]W[D6];)", START);
+        let games = parse_games(&format!("{}\n{}\n", game, game)).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[1].moves[0].eval, Some(-2.5));
+        assert_eq!(games[1].moves[0].time, Some(3.0));
+        assert_eq!(format_move(&games[1].moves[0]), "F5/-2.50/3.00");
+        assert!(parse_games(&format!("{}\n(;GM[Othello]BO[{}]B[A1];)", game, START)).err().unwrap().starts_with("game 2"));
+        assert_eq!(parse_games("").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_record() {
+        let f5 = Ply::from_notation("f5").unwrap();
+        let mut game = GgfGame::from_history(&START_BOARD, &[(START_BOARD, f5)], "a", "b");
+        game.set_game_result(&GameResult::lost(&play(&START_BOARD, f5), Player::White, ResultReason::Timeout));
+        game.set_date(UNIX_EPOCH + std::time::Duration::from_secs(1709316312));
+        assert_eq!(game.tag("RE"), Some("+62.000:t"));
+        assert_eq!(game.result(), Some(62));
+        assert_eq!(game.tag("DT"), Some("2024.03.01_18:05:12.GMT"));
+        assert_eq!(game.moves.len(), 1);
+    }
+
+    #[test]
+    fn test_write_game() {
+        let mut game = GgfGame::new(&START_BOARD, "a", "b");
+        let mut board = START_BOARD;
+        for notation in ["f5", "f6"] {
+            let ply = Ply::from_notation(notation).unwrap();
+            game.push_ply(&board, ply);
+            board = play(&board, ply);
+        }
+        game.set_result(3, false);
+        let text = game.to_string();
+        assert_eq!(text, format!("(;GM[Othello]PC[revello]PB[a]PW[b]RE[+3.000]TY[8]BO[{}]B[F5]W[F6];)", START));
+        assert!(parse_game(&text).unwrap().final_board().unwrap() == board);
+        assert_eq!(parse_game(&text).unwrap().result(), Some(3));
+    }
+
+    #[test]
+    fn test_rejects_illegal_moves() {
+        assert!(parse_game(&format!("(;GM[Othello]BO[{}]B[A1];)", START)).is_err());
+        assert!(parse_game(&format!("(;GM[Othello]BO[{}]W[F5];)", START)).is_err());
+        assert!(parse_game(&format!("(;GM[Othello]BO[{}]B[PA];)", START)).is_err());
+        assert!(parse_game("(;GM[Othello]B[F5];)").is_err());
+    }
+}
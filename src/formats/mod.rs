@@ -0,0 +1,6 @@
+pub mod ggf;
+pub mod wthor;
+pub mod obf;
+pub mod transcript;
+pub mod position_db;
+pub mod diagram;
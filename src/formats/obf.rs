@@ -0,0 +1,114 @@
+// OBF (Othello Board Format) positions, as used by Edax and the endgame test suites.
+//
+// A position is one line: the 64 squares row by row from the top, with `X` black, `O` white
+// and `-` empty, the side to move, and optionally scored moves, best first:
+//   --XXXXX--OOOXX-O-OOOXXOX-OXOXOXXOXXXOXXX--XOXOXX-XXXOOO--OOOOO-- X; G8:+18; H1:+12;
+// Scores are the final disc difference for the side to move with perfect play, empty squares
+// going to the winner. Lines starting with `%` are comments.
+
+use crate::board::board::{Board, Ply, Player, possible_plys};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone)]
+pub struct ObfPosition {
+    pub board: Board,
+    // known moves with their score for the side to move
+    pub moves: Vec<(Ply, i32)>,
+}
+
+impl ObfPosition {
+    pub fn new(board: Board) -> Self {
+        ObfPosition {
+            board: board,
+            moves: Vec::new(),
+        }
+    }
+
+    pub fn best_score(&self) -> Option<i32> {
+        self.moves.iter().map(|(_, score)| *score).max()
+    }
+
+    pub fn best_moves(&self) -> Vec<Ply> {
+        // every move reaching the best score
+        let best = self.best_score();
+        self.moves.iter().filter(|(_, score)| Some(*score) == best).map(|(ply, _)| *ply).collect()
+    }
+}
+
+impl FromStr for ObfPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split(';').map(|field| field.trim());
+        let position = fields.next().unwrap_or("");
+        let board: Board = position.parse()?;
+        if board.turn.is_none() {
+            return Err(format!("expected the squares and the side to move: {:?}", position));
+        }
+        if possible_plys(&board).is_zero() {
+            return Err("the side to move has to pass".to_string());
+        }
+
+        let mut moves = Vec::new();
+        for field in fields.filter(|field| !field.is_empty()) {
+            let (notation, score) = field.split_once(':').ok_or(format!("expected move:score, got {:?}", field))?;
+            let ply = Ply::from_notation(&notation.trim().to_lowercase()).ok_or(format!("invalid move {:?}", notation))?;
+            if !ply.is_in(possible_plys(&board)) {
+                return Err(format!("illegal move {}", notation));
+            }
+            let score = score.trim().parse::<i32>().map_err(|_| format!("invalid score {:?}", score))?;
+            moves.push((ply, score));
+        }
+        Ok(ObfPosition { board: board, moves: moves })
+    }
+}
+
+impl fmt::Display for ObfPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for index in 0..64 {
+            let mask = 1u64 << index;
+            let square = if self.board.black & mask != 0 { 'X' } else if self.board.white & mask != 0 { 'O' } else { '-' };
+            write!(f, "{}", square)?;
+        }
+        write!(f, " {};", if self.board.turn == Some(Player::White) { 'O' } else { 'X' })?;
+        for (ply, score) in &self.moves {
+            write!(f, " {}:{:+};", ply.to_notation().to_uppercase(), score)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_obf(text: &str) -> Result<Vec<ObfPosition>, String> {
+    // every position of a file, skipping blank lines and comments
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('%'))
+        .map(|(i, line)| line.parse().map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::START_BOARD;
+
+    #[test]
+    fn test_parse_and_write() {
+        let line = "---------------------------OX------XO--------------------------- X; F5:+0; D3:+0;";
+        let position: ObfPosition = line.parse().unwrap();
+        assert!(position.board == START_BOARD);
+        assert_eq!(position.best_score(), Some(0));
+        assert_eq!(position.best_moves().len(), 2);
+        assert_eq!(position.to_string(), line);
+
+        let positions = parse_obf(&format!("% a comment\n\n{}\n{}", line, "---------------------------OX------XO--------------------------- O;")).unwrap();
+        assert_eq!(positions.len(), 2);
+        assert!(positions[1].board.turn == Some(Player::White));
+        assert!(positions[1].moves.is_empty());
+
+        assert!(parse_obf("---------------------------OX------XO--------------------------- X; A1:+2;").err().unwrap().starts_with("line 1"));
+        assert!("--- X;".parse::<ObfPosition>().is_err());
+    }
+}
@@ -0,0 +1,318 @@
+// Position databases
+//
+// Indexes a collection of games by the positions they reach, to answer which games reached a
+// position, how they ended and what was played next. Positions are folded over the 8
+// symmetries of the board: each is stored once, as its canonical board, with the next moves
+// turned the same way, and a query turns them back to the orientation it was asked in.
+//
+// The index is written to disk in little endian binary:
+//   "RVDB" and a version byte
+//   the number of games (4 bytes), then for each game the black and white names (a 2 byte
+//   length and utf-8 text each), 1 if the result is known and the disc difference for black
+//   (1 signed byte)
+//   the number of positions (4 bytes), then for each the black and white discs (8 bytes each),
+//   the side to move (0 none, 1 black, 2 white), the number of games reaching it (4 bytes),
+//   and for each of them the game number (4 bytes) and the square of the next move (1 byte,
+//   64 at the end of the game)
+
+use crate::board::board::{Board, Ply, Player, possible_plys, play};
+use crate::formats::ggf::GgfGame;
+
+use std::collections::HashMap;
+
+const MAGIC: &[u8] = b"RVDB";
+const VERSION: u8 = 1;
+// the square of the next move once the game is over
+const NO_MOVE: u8 = 64;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct DbGame {
+    pub black: String,
+    pub white: String,
+    // final disc difference for black
+    pub result: Option<i32>,
+}
+
+#[derive(Default)]
+pub struct PositionDb {
+    pub games: Vec<DbGame>,
+    // the games reaching each canonical board, with the move played next
+    positions: HashMap<Board, Vec<(u32, Option<Ply>)>>,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Outcomes {
+    // for the side to move, black once the game is over
+    pub games: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    // games with a known result, and the sum of their disc differences
+    pub results: u32,
+    pub total_score: i64,
+}
+
+pub struct PositionStats {
+    // the games reaching the position, by number in the database
+    pub games: Vec<u32>,
+    pub outcomes: Outcomes,
+    // the next moves, turned to the position asked for, most played first
+    pub moves: Vec<(Ply, Outcomes)>,
+}
+
+impl Outcomes {
+    fn add(&mut self, score: Option<i32>) {
+        self.games += 1;
+        let Some(score) = score else {
+            return;
+        };
+        match score {
+            1.. => self.wins += 1,
+            0 => self.draws += 1,
+            _ => self.losses += 1,
+        }
+        self.results += 1;
+        self.total_score += score as i64;
+    }
+
+    pub fn win_rate(&self) -> Option<f64> {
+        // draws count half
+        if self.results == 0 {
+            return None;
+        }
+        Some((self.wins as f64 + 0.5 * self.draws as f64) / self.results as f64)
+    }
+
+    pub fn average_score(&self) -> Option<f64> {
+        if self.results == 0 {
+            return None;
+        }
+        Some(self.total_score as f64 / self.results as f64)
+    }
+}
+
+impl PositionDb {
+    pub fn new() -> Self {
+        PositionDb::default()
+    }
+
+    pub fn positions(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn add_game(&mut self, game: &GgfGame) -> Result<(), String> {
+        let number = self.games.len() as u32;
+        let mut reached = Vec::new();
+        for (board, ggf_move) in game.positions()? {
+            // `play` passes by itself, the board of a pass comes again with the next move
+            if let Some(ply) = ggf_move.ply {
+                reached.push((board, Some(ply)));
+            }
+        }
+        reached.push((game.final_board()?, None));
+
+        for (board, ply) in reached {
+            let (canonical, symmetry) = board.canonical();
+            let ply = ply.map(|ply| ply.symmetry(symmetry));
+            self.positions.entry(canonical).or_default().push((number, ply));
+        }
+        self.games.push(DbGame {
+            black: game.tag("PB").unwrap_or("?").to_string(),
+            white: game.tag("PW").unwrap_or("?").to_string(),
+            result: game.result(),
+        });
+        Ok(())
+    }
+
+    pub fn query(&self, board: &Board) -> Option<PositionStats> {
+        // None if no game reached the position
+        let (canonical, symmetry) = board.canonical();
+        let reached = self.positions.get(&canonical)?;
+        let legal: Vec<Ply> = possible_plys(board).into_iter().collect();
+
+        let mut stats = PositionStats { games: Vec::new(), outcomes: Outcomes::default(), moves: Vec::new() };
+        for (number, ply) in reached {
+            let result = self.games[*number as usize].result;
+            let score = if board.turn == Some(Player::White) { result.map(|discs| -discs) } else { result };
+            stats.games.push(*number);
+            stats.outcomes.add(score);
+
+            let Some(ply) = ply.and_then(|ply| legal.iter().find(|legal| legal.symmetry(symmetry) == ply)) else {
+                continue;
+            };
+            // moves that are the same up to a symmetry of the position come back as one
+            let next = play(board, *ply).canonical().0;
+            match stats.moves.iter_mut().find(|(played, _)| play(board, *played).canonical().0 == next) {
+                Some((_, outcomes)) => outcomes.add(score),
+                None => {
+                    let mut outcomes = Outcomes::default();
+                    outcomes.add(score);
+                    stats.moves.push((*ply, outcomes));
+                }
+            }
+        }
+        stats.moves.sort_by_key(|(ply, outcomes)| (std::cmp::Reverse(outcomes.games), ply.to_notation()));
+        Some(stats)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend((self.games.len() as u32).to_le_bytes());
+        for game in &self.games {
+            for name in [&game.black, &game.white] {
+                bytes.extend((name.len() as u16).to_le_bytes());
+                bytes.extend(name.as_bytes());
+            }
+            bytes.push(game.result.is_some() as u8);
+            bytes.push(game.result.unwrap_or(0) as i8 as u8);
+        }
+
+        // sorted, so that the same games always give the same file
+        let mut positions: Vec<_> = self.positions.iter().collect();
+        positions.sort_by_key(|(board, _)| (board.black, board.white, side_byte(board)));
+        bytes.extend((positions.len() as u32).to_le_bytes());
+        for (board, reached) in positions {
+            bytes.extend(board.black.to_le_bytes());
+            bytes.extend(board.white.to_le_bytes());
+            bytes.push(side_byte(board));
+            bytes.extend((reached.len() as u32).to_le_bytes());
+            for (number, ply) in reached {
+                bytes.extend(number.to_le_bytes());
+                bytes.push(ply.map_or(NO_MOVE, |ply| {
+                    let (row, col) = ply.to_row_col();
+                    (row * 8 + col) as u8
+                }));
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let mut reader = Reader { bytes: bytes, position: 0 };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err("not a position database".to_string());
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            return Err(format!("unsupported database version {}", version));
+        }
+
+        let mut db = PositionDb::new();
+        for _ in 0..reader.u32()? {
+            let black = reader.name()?;
+            let white = reader.name()?;
+            let known = reader.take(1)?[0] != 0;
+            let discs = reader.take(1)?[0] as i8 as i32;
+            db.games.push(DbGame { black: black, white: white, result: if known { Some(discs) } else { None } });
+        }
+        for _ in 0..reader.u32()? {
+            let black = reader.u64()?;
+            let white = reader.u64()?;
+            let turn = match reader.take(1)?[0] {
+                0 => None,
+                1 => Some(Player::Black),
+                2 => Some(Player::White),
+                side => return Err(format!("invalid side to move {}", side)),
+            };
+            let board = Board::new(black, white, turn).ok_or("a square holds two discs")?;
+            let mut reached = Vec::new();
+            for _ in 0..reader.u32()? {
+                let number = reader.u32()?;
+                if number as usize >= db.games.len() {
+                    return Err(format!("no game {}", number));
+                }
+                let ply = match reader.take(1)?[0] {
+                    NO_MOVE => None,
+                    square => Some(Ply::from_row_col(square as usize / 8, square as usize % 8).ok_or(format!("invalid square {}", square))?),
+                };
+                reached.push((number, ply));
+            }
+            db.positions.insert(board, reached);
+        }
+        if reader.position != bytes.len() {
+            return Err("trailing bytes after the positions".to_string());
+        }
+        Ok(db)
+    }
+}
+
+fn side_byte(board: &Board) -> u8 {
+    match board.turn {
+        None => 0,
+        Some(Player::Black) => 1,
+        Some(Player::White) => 2,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let bytes = self.bytes.get(self.position..self.position + n).ok_or("the database is cut short")?;
+        self.position += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let length = u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")) as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|_| "a name is not utf-8".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::{START_BOARD, play_moves};
+    use crate::formats::ggf::parse_games;
+
+    const GAMES: &str = "\
+        (;GM[Othello]PB[a]PW[b]RE[+10.000]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[F5]W[D6]B[C3];)\n\
+        (;GM[Othello]PB[c]PW[d]RE[-4.000:t]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[E6]W[F6];)\n\
+        (;GM[Othello]PB[e]PW[f]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[D3]W[C5];)\n";
+
+    #[test]
+    fn test_query() {
+        let mut db = PositionDb::new();
+        for game in parse_games(GAMES).unwrap() {
+            db.add_game(&game).unwrap();
+        }
+
+        // the four first moves are the same up to symmetry
+        let start = db.query(&START_BOARD).unwrap();
+        assert_eq!(start.games, vec![0, 1, 2]);
+        assert_eq!(start.moves.len(), 1);
+        assert_eq!((start.outcomes.wins, start.outcomes.losses, start.outcomes.results), (1, 1, 2));
+        assert_eq!(start.outcomes.average_score(), Some(3.0));
+
+        // d3 c5 is f5 d6 turned over the other diagonal, and e6 f6 is f5 f6 transposed
+        let board = play_moves(&START_BOARD, "f5").unwrap();
+        let stats = db.query(&board).unwrap();
+        assert_eq!(stats.games.len(), 3);
+        let moves: Vec<(String, u32)> = stats.moves.iter().map(|(ply, outcomes)| (ply.to_notation(), outcomes.games)).collect();
+        assert_eq!(moves, [("d6".to_string(), 2), ("f6".to_string(), 1)]);
+        // scores are for white, to move, and the third game has no result
+        assert_eq!((stats.moves[0].1.results, stats.moves[0].1.average_score()), (1, Some(-10.0)));
+        assert_eq!(stats.moves[1].1.win_rate(), Some(1.0));
+
+        assert!(db.query(&play_moves(&START_BOARD, "f5f4").unwrap()).is_none());
+
+        let bytes = db.to_bytes();
+        let read = PositionDb::from_bytes(&bytes).unwrap();
+        assert_eq!(read.games, db.games);
+        assert_eq!(read.positions(), db.positions());
+        assert_eq!(read.to_bytes(), bytes);
+        assert!(PositionDb::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+// Grid transcripts, the scoresheets of over-the-board games
+//
+// Each square of the 8x8 grid holds the number of the move played on it, from 1. Passes are
+// not written: the next number simply belongs to the same player again. The four starting
+// discs and the squares left empty hold no number. A sheet looks like
+//      a  b  c  d  e  f  g  h
+//   1 25 24 23 22 36 37 38 56
+//   2  .  .  .  .  .  .  .  .
+//   ...
+// with `.`, `-`, `0`, `X`, `O` or `*` for a square without a number. The row labels and the
+// column header are optional.
+//
+// Sheets start from the usual position unless given another one. Reading a sheet replays it
+// move by move from there, so that numbering mistakes (a number used twice, a gap, a disc
+// that could not have been played there) are reported with the move where the game stops
+// making sense.
+
+use crate::board::board::{Board, Ply, START_BOARD, possible_plys, play};
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, PartialEq)]
+pub struct Transcript {
+    pub start: Board,
+    // the move number of every square, 0 for none
+    pub numbers: [u8; 64],
+}
+
+impl Transcript {
+    pub fn from_moves(start: &Board, moves: &[Ply]) -> Self {
+        let mut numbers = [0; 64];
+        for (i, ply) in moves.iter().enumerate() {
+            let (row, col) = ply.to_row_col();
+            numbers[row * 8 + col] = (i + 1) as u8;
+        }
+        Transcript { start: start.clone(), numbers: numbers }
+    }
+
+    pub fn with_start(mut self, start: &Board) -> Self {
+        self.start = start.clone();
+        self
+    }
+
+    pub fn moves(&self) -> Result<Vec<Ply>, String> {
+        Ok(self.replay()?.into_iter().map(|(_, ply)| ply).collect())
+    }
+
+    pub fn replay(&self) -> Result<Vec<(Board, Ply)>, String> {
+        // every move in order with the board it was played on, checked by replaying them
+        let start = &self.start;
+        let mut squares: Vec<Option<usize>> = vec![None; 61];
+        for (index, number) in self.numbers.iter().enumerate() {
+            let number = *number as usize;
+            if number == 0 {
+                continue;
+            }
+            if number > 60 {
+                return Err(format!("move {} on {}: there are only 60 moves", number, square_name(index)));
+            }
+            if let Some(other) = squares[number] {
+                return Err(format!("move {} appears on both {} and {}", number, square_name(other), square_name(index)));
+            }
+            if (start.black | start.white) & (1 << index) != 0 {
+                return Err(format!("move {} on {}: the square is taken from the start", number, square_name(index)));
+            }
+            squares[number] = Some(index);
+        }
+
+        let last = squares.iter().rposition(|square| square.is_some()).unwrap_or(0);
+        let mut board = start.clone();
+        let mut history = Vec::new();
+        for (number, square) in squares.iter().enumerate().take(last + 1).skip(1) {
+            let index = square.ok_or(format!("move {} is missing", number))?;
+            let ply = Ply::from_row_col(index / 8, index % 8).expect("the index is on the board");
+            if board.turn.is_none() {
+                return Err(format!("move {} on {}: the game was already over", number, square_name(index)));
+            }
+            // `play` already passed for a player without a move
+            if !ply.is_in(possible_plys(&board)) {
+                return Err(format!("move {} on {} is not a legal move", number, square_name(index)));
+            }
+            history.push((board.clone(), ply));
+            board = play(&board, ply);
+        }
+        Ok(history)
+    }
+}
+
+fn square_name(index: usize) -> String {
+    Ply::from_row_col(index / 8, index % 8).expect("the index is on the board").to_notation()
+}
+
+impl FromStr for Transcript {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut numbers = [0; 64];
+        let mut row = 0;
+        for line in s.lines() {
+            let mut cells: Vec<&str> = line.split_whitespace().collect();
+            // blank lines and the column header
+            if cells.is_empty() || cells.iter().all(|cell| cell.len() == 1 && ('a'..='h').contains(&cell.chars().next().unwrap())) {
+                continue;
+            }
+            if cells.len() == 9 {
+                // a row label
+                cells.remove(0);
+            }
+            if cells.len() != 8 {
+                return Err(format!("row {} has {} squares instead of 8: {:?}", row + 1, cells.len(), line));
+            }
+            if row == 8 {
+                return Err("a sheet has 8 rows".to_string());
+            }
+            for (col, cell) in cells.iter().enumerate() {
+                numbers[row * 8 + col] = match *cell {
+                    "." | "-" | "X" | "x" | "O" | "o" | "*" => 0,
+                    number => number.parse::<u8>().map_err(|_| format!("invalid square {:?} in row {}", number, row + 1))?,
+                };
+            }
+            row += 1;
+        }
+        if row != 8 {
+            return Err(format!("a sheet has 8 rows, found {}", row));
+        }
+        Ok(Transcript { start: START_BOARD, numbers: numbers })
+    }
+}
+
+impl fmt::Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // the sheet for printing, with the starting discs
+        writeln!(f, "   a  b  c  d  e  f  g  h")?;
+        for row in 0..8 {
+            write!(f, "{}", row + 1)?;
+            for col in 0..8 {
+                let index = row * 8 + col;
+                let mask = 1u64 << index;
+                let cell = if self.numbers[index] != 0 {
+                    self.numbers[index].to_string()
+                } else if self.start.black & mask != 0 {
+                    "X".to_string()
+                } else if self.start.white & mask != 0 {
+                    "O".to_string()
+                } else {
+                    ".".to_string()
+                };
+                write!(f, " {:>2}", cell)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::Player;
+
+    fn moves(notation: &str) -> Vec<Ply> {
+        notation.as_bytes().chunks(2).map(|ply| Ply::from_notation(std::str::from_utf8(ply).unwrap()).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let game = moves("f5d6c3d3c4f4f6f3e6e7");
+        let sheet = Transcript::from_moves(&START_BOARD, &game).to_string();
+        assert!(sheet.starts_with("   a  b  c  d  e  f  g  h\n"));
+        assert!(sheet.contains("\n3  .  .  3  4  .  8  .  .\n"));
+        assert!(sheet.contains("\n4  .  .  5  O  X  6  .  .\n"));
+        let transcript: Transcript = sheet.parse().unwrap();
+        assert!(transcript.moves().unwrap() == game);
+    }
+
+    #[test]
+    fn test_passes() {
+        // black has no move after move 14, so white plays 14 and 15
+        let mut game = moves("d3c3b3b2b1a1c4c1c2c5d1f5b4a4");
+        let board = game.iter().fold(START_BOARD, |board, ply| play(&board, *ply));
+        game.push(possible_plys(&board).to_vec_ply()[0]);
+        let history = Transcript::from_moves(&START_BOARD, &game).replay().unwrap();
+        assert!(history[13].0.turn == Some(Player::White) && history[14].0.turn == Some(Player::White));
+        assert!(history.into_iter().map(|(_, ply)| ply).collect::<Vec<Ply>>() == game);
+
+        // neither side can move after move 9
+        let mut transcript = Transcript::from_moves(&START_BOARD, &moves("d3c3b3d2e1d6d7e3f4"));
+        assert_eq!(transcript.moves().unwrap().len(), 9);
+        transcript.numbers[63] = 10;
+        assert_eq!(transcript.moves().err().unwrap(), "move 10 on h8: the game was already over");
+    }
+
+    #[test]
+    fn test_errors() {
+        let transcript = Transcript::from_moves(&START_BOARD, &moves("f5d6c3d3c4"));
+        let mut broken = transcript.clone();
+        broken.numbers[63] = 4;
+        assert_eq!(broken.moves().err().unwrap(), "move 4 appears on both d3 and h8");
+
+        let mut broken = transcript.clone();
+        broken.numbers[19] = 0;
+        assert_eq!(broken.moves().err().unwrap(), "move 4 is missing");
+
+        let mut broken = transcript.clone();
+        broken.numbers.swap(18, 19);
+        assert_eq!(broken.moves().err().unwrap(), "move 3 on d3 is not a legal move");
+
+        let mut broken = transcript;
+        broken.numbers[27] = 6;
+        assert_eq!(broken.moves().err().unwrap(), "move 6 on d4: the square is taken from the start");
+
+        assert!("1 2 3".parse::<Transcript>().is_err());
+    }
+}
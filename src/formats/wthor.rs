@@ -0,0 +1,235 @@
+// WTHOR databases of the French Othello Federation
+//
+// Tournament games come in `.wtb` files, one per year, with the names of the players and the
+// tournaments in `WTHOR.JOU` and `WTHOR.TRN`. Every file starts with a 16 byte header:
+//   0      century the file was written
+//   1..4   year (in the century), month and day it was written
+//   4..8   number of games (little endian)
+//   8..10  number of players or tournaments, in the name files
+//   10..12 year the games were played
+//   12     board size, 0 or 8 for 8x8
+//   13     1 for solitaire games
+//   14     empty squares from which the theoretical scores are perfect play
+// A game takes 68 bytes: the tournament, black and white numbers (2 bytes each), the number
+// of black discs at the end, the theoretical number of black discs, and 60 moves written as
+// 10 * row + column (both from 1), `0` once the game is over. Passes are not written.
+// Names are 20 bytes for a player and 26 for a tournament, latin-1 text padded with zeros.
+// Scores give the empty squares to the winner.
+
+use crate::board::board::{Board, Ply, START_BOARD, Player, possible_plys, play};
+use crate::formats::ggf::GgfGame;
+
+const HEADER_SIZE: usize = 16;
+const GAME_SIZE: usize = 68;
+pub const PLAYER_NAME_SIZE: usize = 20;
+pub const TOURNAMENT_NAME_SIZE: usize = 26;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WthorHeader {
+    // when the file was written
+    pub created: (u16, u8, u8),
+    pub records: u32,
+    pub names: u16,
+    // year the games were played
+    pub year: u16,
+    pub solitaire: bool,
+    // empty squares from which the theoretical scores are exact
+    pub theoretical_depth: u8,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct WthorGame {
+    pub tournament: u16,
+    pub black: u16,
+    pub white: u16,
+    pub black_discs: u8,
+    // black discs after perfect play from `theoretical_depth` empties
+    pub theoretical_black_discs: u8,
+    pub moves: Vec<Ply>,
+}
+
+fn u16_at(bytes: &[u8], index: usize) -> u16 {
+    u16::from_le_bytes([bytes[index], bytes[index + 1]])
+}
+
+pub fn parse_header(bytes: &[u8]) -> Result<WthorHeader, String> {
+    if bytes.len() < HEADER_SIZE {
+        return Err("a WTHOR file starts with a 16 byte header".to_string());
+    }
+    if bytes[12] != 0 && bytes[12] != 8 {
+        return Err(format!("unsupported board size {}", bytes[12]));
+    }
+    Ok(WthorHeader {
+        created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+        records: u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        names: u16_at(bytes, 8),
+        year: u16_at(bytes, 10),
+        solitaire: bytes[13] == 1,
+        theoretical_depth: bytes[14],
+    })
+}
+
+fn parse_move(code: u8) -> Result<Ply, String> {
+    let (row, col) = (code / 10, code % 10);
+    if !(1..=8).contains(&row) || !(1..=8).contains(&col) {
+        return Err(format!("invalid move code {}", code));
+    }
+    Ok(Ply::from_row_col((row - 1) as usize, (col - 1) as usize).expect("row and column are on the board"))
+}
+
+pub fn parse_games(bytes: &[u8]) -> Result<(WthorHeader, Vec<WthorGame>), String> {
+    // the header and the games of a `.wtb` file
+    let header = parse_header(bytes)?;
+    if header.solitaire {
+        return Err("solitaire files are not supported".to_string());
+    }
+    let body = &bytes[HEADER_SIZE..];
+    if body.len() != header.records as usize * GAME_SIZE {
+        return Err(format!("the header announces {} games, the file holds {} bytes of games", header.records, body.len()));
+    }
+
+    let mut games = Vec::new();
+    for (i, record) in body.chunks(GAME_SIZE).enumerate() {
+        let moves = record[8..].iter()
+            .take_while(|code| **code != 0)
+            .map(|code| parse_move(*code))
+            .collect::<Result<Vec<Ply>, String>>()
+            .map_err(|e| format!("game {}: {}", i + 1, e))?;
+        games.push(WthorGame {
+            tournament: u16_at(record, 0),
+            black: u16_at(record, 2),
+            white: u16_at(record, 4),
+            black_discs: record[6],
+            theoretical_black_discs: record[7],
+            moves: moves,
+        });
+    }
+    Ok((header, games))
+}
+
+pub fn parse_names(bytes: &[u8], name_size: usize) -> Result<Vec<String>, String> {
+    // the names of a `.jou` (`PLAYER_NAME_SIZE`) or `.trn` (`TOURNAMENT_NAME_SIZE`) file,
+    // by number
+    let header = parse_header(bytes)?;
+    let body = &bytes[HEADER_SIZE..];
+    if body.len() < header.names as usize * name_size {
+        return Err(format!("the header announces {} names, the file is too short", header.names));
+    }
+    Ok(body.chunks(name_size)
+        .take(header.names as usize)
+        .map(|name| {
+            // latin-1 maps one to one to the first unicode characters
+            let name: String = name.iter().take_while(|c| **c != 0).map(|c| *c as char).collect();
+            name.trim().to_string()
+        })
+        .collect())
+}
+
+impl WthorGame {
+    pub fn replay(&self) -> Result<Vec<(Board, Ply)>, String> {
+        // every move with the board it was played on, checked with `play`
+        let mut board = START_BOARD;
+        let mut history = Vec::new();
+        for (i, ply) in self.moves.iter().enumerate() {
+            if board.turn.is_none() || !ply.is_in(possible_plys(&board)) {
+                return Err(format!("move {}: illegal move {}", i + 1, ply.to_notation()));
+            }
+            history.push((board.clone(), *ply));
+            board = play(&board, *ply);
+        }
+        Ok(history)
+    }
+
+    pub fn final_board(&self) -> Result<Board, String> {
+        let history = self.replay()?;
+        Ok(history.last().map_or(START_BOARD, |(board, ply)| play(board, *ply)))
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        match self.black_discs {
+            33.. => Some(Player::Black),
+            32 => None,
+            _ => Some(Player::White),
+        }
+    }
+
+    pub fn to_ggf(&self, header: &WthorHeader, players: &[String], tournaments: &[String]) -> Result<GgfGame, String> {
+        // Names missing from the name files are replaced by their numbers. GGF dates need the
+        // time of day, so the year goes in the comment with the theoretical score.
+        let name = |names: &[String], number: u16| names.get(number as usize).cloned().unwrap_or(number.to_string());
+        let mut game = GgfGame::from_history(&START_BOARD, &self.replay()?, &name(players, self.black), &name(players, self.white));
+        game.set_tag("PC", &name(tournaments, self.tournament));
+        game.set_tag("C", &format!(
+            "WTHOR {}, perfect play from {} empties: {:+}",
+            header.year, header.theoretical_depth, 2 * self.theoretical_black_discs as i32 - 64,
+        ));
+        game.set_result(2 * self.black_discs as i32 - 64, false);
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(records: u32, names: u16, year: u16) -> Vec<u8> {
+        let mut bytes = vec![20, 24, 3, 1];
+        bytes.extend(records.to_le_bytes());
+        bytes.extend(names.to_le_bytes());
+        bytes.extend(year.to_le_bytes());
+        bytes.extend([8, 0, 22, 0]);
+        bytes
+    }
+
+    fn game(tournament: u16, black: u16, white: u16, black_discs: u8, moves: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend(tournament.to_le_bytes());
+        bytes.extend(black.to_le_bytes());
+        bytes.extend(white.to_le_bytes());
+        bytes.extend([black_discs, 40]);
+        bytes.extend(moves);
+        bytes.resize(GAME_SIZE, 0);
+        bytes
+    }
+
+    fn names(names: &[&[u8]], size: usize) -> Vec<u8> {
+        let mut bytes = header(0, names.len() as u16, 0);
+        for name in names {
+            let mut record = name.to_vec();
+            record.resize(size, 0);
+            bytes.extend(record);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_games() {
+        // f5 d6 c3 d3 c4, then an illegal a1
+        let mut bytes = header(2, 0, 1997);
+        bytes.extend(game(1, 0, 1, 40, &[56, 64, 33, 34, 43]));
+        bytes.extend(game(0, 1, 0, 20, &[56, 11]));
+        let (header, games) = parse_games(&bytes).unwrap();
+        assert_eq!(header.year, 1997);
+        assert_eq!(header.created, (2024, 3, 1));
+        assert_eq!(header.theoretical_depth, 22);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].moves[1].to_notation(), "d6");
+        assert_eq!(games[0].theoretical_black_discs, 40);
+        assert_eq!(games[0].winner(), Some(Player::Black));
+        assert_eq!(games[0].final_board().unwrap().count_pieces(), 9);
+        assert!(games[1].replay().is_err());
+
+        let players = parse_names(&names(&[b"Tamenori Hideshi", b"Shaman Andr\xe9"], PLAYER_NAME_SIZE), PLAYER_NAME_SIZE).unwrap();
+        assert_eq!(players[1], "Shaman André");
+        let ggf = games[0].to_ggf(&header, &players, &[]).unwrap();
+        assert_eq!(ggf.tag("PB"), Some("Tamenori Hideshi"));
+        assert_eq!(ggf.tag("PC"), Some("1"));
+        assert_eq!(ggf.tag("C"), Some("WTHOR 1997, perfect play from 22 empties: +16"));
+        assert_eq!(ggf.tag("DT"), None);
+        assert_eq!(ggf.tag("RE"), Some("+16.000"));
+        assert_eq!(ggf.moves.len(), 5);
+
+        // a file cut short
+        assert!(parse_games(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
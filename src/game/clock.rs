@@ -0,0 +1,389 @@
+// Chess style game clock
+//
+// Each side has its own time control: a base time, a Fischer increment added after every move,
+// and a Bronstein delay, the first part of every turn that does not count (nothing is added
+// back beyond the time actually used). Only the side to move has its clock running. Time used
+// after a clock reached zero is kept as overtime, for the rules that do not end the game on
+// time.
+//
+// Time controls are written like `10m+5s` (10 minutes, 5 seconds added per move), `1h30m`,
+// `5m+d3s` (3 seconds of delay per move) or plain seconds, `300`.
+
+use crate::board::board::Player;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TimeControl {
+    pub base: Duration,
+    // added after every move
+    pub increment: Duration,
+    // free time at the start of every turn
+    pub delay: Duration,
+}
+
+impl TimeControl {
+    pub fn new(base: Duration) -> Self {
+        TimeControl {
+            base: base,
+            increment: Duration::ZERO,
+            delay: Duration::ZERO,
+        }
+    }
+
+    pub fn with_increment(mut self, increment: Duration) -> Self {
+        self.increment = increment;
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    // `1h30m`, `10m`, `2.5s`, `90` (seconds)
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("empty duration".to_string());
+    }
+    if let Ok(seconds) = s.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).map_err(|e| format!("{}: {}", s, e));
+    }
+    let mut total = 0.0;
+    let mut number = String::new();
+    for c in s.chars() {
+        let unit = match c {
+            'h' => 3600.0,
+            'm' => 60.0,
+            's' => 1.0,
+            _ => {
+                number.push(c);
+                continue;
+            }
+        };
+        let value = number.parse::<f64>().map_err(|_| format!("invalid duration: {}", s))?;
+        total += value * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("missing unit in duration: {}", s));
+    }
+    Duration::try_from_secs_f64(total).map_err(|e| format!("{}: {}", s, e))
+}
+
+fn format_duration(time: Duration) -> String {
+    let seconds = time.as_secs();
+    let mut text = String::new();
+    if seconds >= 3600 {
+        text += &format!("{}h", seconds / 3600);
+    }
+    if seconds % 3600 >= 60 {
+        text += &format!("{}m", seconds % 3600 / 60);
+    }
+    if !seconds.is_multiple_of(60) || text.is_empty() {
+        text += &format!("{}s", seconds % 60);
+    }
+    text
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // `<base>[+<increment>][+d<delay>]`
+        let mut parts = s.split('+');
+        let base = parse_duration(parts.next().unwrap_or(""))?;
+        let mut control = TimeControl::new(base);
+        for part in parts {
+            let part = part.trim();
+            match part.strip_prefix('d') {
+                Some(delay) => control.delay = parse_duration(delay)?,
+                None => control.increment = parse_duration(part)?,
+            }
+        }
+        Ok(control)
+    }
+}
+
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format_duration(self.base))?;
+        if !self.increment.is_zero() {
+            write!(f, "+{}", format_duration(self.increment))?;
+        }
+        if !self.delay.is_zero() {
+            write!(f, "+d{}", format_duration(self.delay))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn format_clock(time: Duration) -> String {
+    // `1:05:03` from an hour on, `4:59` below
+    let seconds = time.as_secs();
+    if seconds >= 3600 {
+        return format!("{}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    }
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SideClock {
+    control: TimeControl,
+    remaining: Duration,
+    overtime: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+    black: SideClock,
+    white: SideClock,
+    // the side whose turn it is
+    turn: Option<Player>,
+    // when the clock of the side to move was last started, None while paused or stopped
+    started: Option<Instant>,
+    // delay left in the current turn
+    delay_left: Duration,
+}
+
+impl Clock {
+    pub fn new(black: TimeControl, white: TimeControl) -> Self {
+        let side = |control: TimeControl| SideClock {
+            control: control,
+            remaining: control.base,
+            overtime: Duration::ZERO,
+        };
+        Clock {
+            black: side(black),
+            white: side(white),
+            turn: None,
+            started: None,
+            delay_left: Duration::ZERO,
+        }
+    }
+
+    fn side(&self, player: Player) -> &SideClock {
+        match player {
+            Player::Black => &self.black,
+            Player::White => &self.white,
+        }
+    }
+
+    fn side_mut(&mut self, player: Player) -> &mut SideClock {
+        match player {
+            Player::Black => &mut self.black,
+            Player::White => &mut self.white,
+        }
+    }
+
+    pub fn control(&self, player: Player) -> TimeControl {
+        self.side(player).control
+    }
+
+    pub fn turn(&self) -> Option<Player> {
+        self.turn
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.turn.is_some() && self.started.is_none()
+    }
+
+    fn used(&self, player: Player) -> Duration {
+        // time counted against the player in the current turn, since the last start
+        match (self.turn, self.started) {
+            (Some(turn), Some(started)) if turn == player => started.elapsed().saturating_sub(self.delay_left),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn remaining(&self, player: Player) -> Duration {
+        self.side(player).remaining.saturating_sub(self.used(player))
+    }
+
+    pub fn overtime(&self, player: Player) -> Duration {
+        let side = self.side(player);
+        side.overtime + self.used(player).saturating_sub(side.remaining)
+    }
+
+    pub fn is_flagged(&self, player: Player) -> bool {
+        self.remaining(player).is_zero()
+    }
+
+    pub fn time_to_flag(&self, player: Player) -> Duration {
+        // how long until the clock of the player reaches zero, counting the delay left
+        if self.turn != Some(player) || self.is_flagged(player) {
+            return self.remaining(player);
+        }
+        let elapsed = self.started.map(|started| started.elapsed()).unwrap_or(Duration::ZERO);
+        (self.side(player).remaining + self.delay_left).saturating_sub(elapsed)
+    }
+
+    fn bank(&mut self) {
+        // moves the time used since the last start into the side clock
+        let Some(turn) = self.turn else {
+            return;
+        };
+        let Some(started) = self.started.take() else {
+            return;
+        };
+        let elapsed = started.elapsed();
+        let used = elapsed.saturating_sub(self.delay_left);
+        self.delay_left = self.delay_left.saturating_sub(elapsed);
+        let side = self.side_mut(turn);
+        side.overtime += used.saturating_sub(side.remaining);
+        side.remaining = side.remaining.saturating_sub(used);
+    }
+
+    pub fn start(&mut self, player: Player) {
+        // starts the turn of the player, ending the running turn without increment
+        self.bank();
+        self.turn = Some(player);
+        self.started = Some(Instant::now());
+        self.delay_left = self.side(player).control.delay;
+    }
+
+    pub fn finish_turn(&mut self) {
+        // the player to move made its move: its clock stops and gets the increment
+        let Some(turn) = self.turn else {
+            return;
+        };
+        self.bank();
+        let side = self.side_mut(turn);
+        side.remaining += side.control.increment;
+        self.turn = None;
+    }
+
+    pub fn stop(&mut self) {
+        // stops the clocks for good, where they are
+        self.bank();
+        self.turn = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.bank();
+    }
+
+    pub fn resume(&mut self) {
+        if self.is_paused() {
+            self.started = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_parse_time_control() {
+        let control: TimeControl = "10m+5s".parse().unwrap();
+        assert_eq!(control, TimeControl::new(Duration::from_secs(600)).with_increment(Duration::from_secs(5)));
+        let control: TimeControl = "1h30m+d3s".parse().unwrap();
+        assert_eq!(control, TimeControl::new(Duration::from_secs(5400)).with_delay(Duration::from_secs(3)));
+        assert_eq!("300".parse::<TimeControl>().unwrap().base, Duration::from_secs(300));
+        assert_eq!("2.5s".parse::<TimeControl>().unwrap().base, Duration::from_millis(2500));
+        assert!("10x".parse::<TimeControl>().is_err());
+        assert!("10m+5".parse::<TimeControl>().is_ok());
+        assert!("".parse::<TimeControl>().is_err());
+
+        for text in ["10m+5s", "1h30m+d3s", "2h", "45s", "1h0m1s+1m"] {
+            let control: TimeControl = text.parse().unwrap();
+            assert_eq!(control.to_string().parse::<TimeControl>().unwrap(), control);
+        }
+        assert_eq!("1h0m1s+1m".parse::<TimeControl>().unwrap().to_string(), "1h1s+1m");
+    }
+
+    #[test]
+    fn test_format_clock() {
+        assert_eq!(format_clock(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_clock(Duration::from_secs(3 * 3600 + 62)), "3:01:02");
+        // more than the ~35 minutes of microseconds an i32 holds
+        assert_eq!(format_clock(Duration::from_secs(40 * 60)), "40:00");
+    }
+
+    #[test]
+    fn test_increment() {
+        let control = TimeControl::new(Duration::from_secs(60)).with_increment(Duration::from_secs(5));
+        let mut clock = Clock::new(control, TimeControl::new(Duration::from_secs(30)));
+        assert_eq!(clock.remaining(Player::Black), Duration::from_secs(60));
+
+        clock.start(Player::Black);
+        thread::sleep(Duration::from_millis(20));
+        assert!(clock.remaining(Player::Black) < Duration::from_secs(60));
+        assert_eq!(clock.remaining(Player::White), Duration::from_secs(30));
+        clock.finish_turn();
+        let black = clock.remaining(Player::Black);
+        assert!(black > Duration::from_secs(64) && black < Duration::from_secs(65));
+
+        // only the side to move is running
+        clock.start(Player::White);
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(clock.remaining(Player::Black), black);
+        clock.finish_turn();
+        assert!(clock.remaining(Player::White) < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_delay() {
+        let control = TimeControl::new(Duration::from_millis(50)).with_delay(Duration::from_millis(100));
+        let mut clock = Clock::new(control, control);
+        clock.start(Player::Black);
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(clock.remaining(Player::Black), Duration::from_millis(50));
+        assert!(clock.time_to_flag(Player::Black) <= Duration::from_millis(120));
+        clock.finish_turn();
+        // the delay is not added back
+        assert_eq!(clock.remaining(Player::Black), Duration::from_millis(50));
+
+        clock.start(Player::White);
+        thread::sleep(Duration::from_millis(200));
+        assert!(clock.is_flagged(Player::White));
+        assert!(clock.overtime(Player::White) >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_pause_and_stop() {
+        let control = TimeControl::new(Duration::from_secs(10));
+        let mut clock = Clock::new(control, control);
+        clock.start(Player::Black);
+        thread::sleep(Duration::from_millis(10));
+        clock.pause();
+        assert!(clock.is_paused());
+        let black = clock.remaining(Player::Black);
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(clock.remaining(Player::Black), black);
+        clock.resume();
+        assert!(!clock.is_paused());
+        thread::sleep(Duration::from_millis(10));
+        assert!(clock.remaining(Player::Black) < black);
+
+        clock.stop();
+        let black = clock.remaining(Player::Black);
+        thread::sleep(Duration::from_millis(10));
+        assert_eq!(clock.remaining(Player::Black), black);
+        assert_eq!(clock.turn(), None);
+    }
+
+    #[test]
+    fn test_overtime() {
+        let mut clock = Clock::new(TimeControl::new(Duration::from_millis(10)), TimeControl::new(Duration::ZERO));
+        clock.start(Player::Black);
+        thread::sleep(Duration::from_millis(30));
+        assert!(clock.is_flagged(Player::Black));
+        assert!(clock.overtime(Player::Black) >= Duration::from_millis(20));
+        clock.finish_turn();
+        let overtime = clock.overtime(Player::Black);
+        assert!(overtime >= Duration::from_millis(20));
+        assert_eq!(clock.remaining(Player::Black), Duration::ZERO);
+
+        // a clock out of time has nothing to wait for
+        clock.start(Player::White);
+        assert_eq!(clock.time_to_flag(Player::White), Duration::ZERO);
+        assert_eq!(clock.overtime(Player::Black), overtime);
+    }
+}
@@ -0,0 +1,4 @@
+pub mod session;
+pub mod result;
+pub mod clock;
+pub mod openings;
@@ -0,0 +1,72 @@
+// Opening names
+//
+// Othello openings are known by names, like the Tiger (f5 d6 c3 d3 c4). The names are kept
+// in a bundled table of move sequences from the start position. An opening is recognised by
+// the position it leads to, folded over the symmetries of the board, so a game starting d3
+// or reaching the position by another order of moves gets the same name. A game is named
+// after the deepest opening it went through.
+
+use crate::board::board::{Board, START_BOARD, play_moves};
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const OPENING_NAMES: &str = include_str!("opening_names.txt");
+
+pub struct OpeningNames {
+    // the name of each canonical position
+    names: HashMap<Board, String>,
+}
+
+impl OpeningNames {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        // Lines of moves and a name, like `f5d6c3d3c4 Tiger`; `#` starts a comment
+        let mut names = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (moves, name) = line.split_once(char::is_whitespace).ok_or(format!("line {}: expected moves and a name", number + 1))?;
+            let board = play_moves(&START_BOARD, moves).map_err(|e| format!("line {}: {}", number + 1, e))?;
+            names.insert(board.canonical().0, name.trim().to_string());
+        }
+        Ok(OpeningNames { names: names })
+    }
+
+    pub fn name(&self, board: &Board) -> Option<&str> {
+        // the name of the opening leading to exactly this position
+        self.names.get(&board.canonical().0).map(|name| name.as_str())
+    }
+
+    pub fn name_of_game<'a, I: IntoIterator<Item = &'a Board>>(&self, boards: I) -> Option<&str> {
+        // the deepest named position among the boards of a game
+        boards.into_iter().filter_map(|board| self.name(board)).last()
+    }
+}
+
+pub fn opening_names() -> &'static OpeningNames {
+    // the bundled table
+    static NAMES: OnceLock<OpeningNames> = OnceLock::new();
+    NAMES.get_or_init(|| OpeningNames::parse(OPENING_NAMES).expect("the bundled opening names are legal"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_names() {
+        let names = opening_names();
+        assert_eq!(names.name(&play_moves(&START_BOARD, "f5d6c3d3c4").unwrap()), Some("Tiger"));
+        // the same position from another first move and another order of moves
+        assert_eq!(names.name(&play_moves(&START_BOARD, "c4e3f6e6f5").unwrap()), Some("Tiger"));
+        assert_eq!(names.name(&play_moves(&START_BOARD, "f5").unwrap()), None);
+
+        let boards: Vec<Board> = ["", "d3", "d3c3", "d3c3c4", "d3c3c4c5"].iter()
+            .map(|moves| play_moves(&START_BOARD, moves).unwrap())
+            .collect();
+        assert_eq!(names.name_of_game(&boards), Some("Diagonal opening"));
+        assert!(OpeningNames::parse("f5f5 Nonsense").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+// The result of a game, scored by the rules of the World Othello Federation:
+// - a game played to the end gives the empty squares to the winner, or splits them on a draw
+// - a game lost on time or by resignation gives the winner its discs and all the empty
+//   squares, but at least 33 discs
+use crate::board::board::{Board, Player};
+
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResultReason {
+    // neither side could move any more
+    Normal,
+    Timeout,
+    // also a player that crashed or answered an illegal move
+    Resignation,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GameResult {
+    // None is a draw
+    pub winner: Option<Player>,
+    pub black_discs: i32,
+    pub white_discs: i32,
+    pub reason: ResultReason,
+}
+
+impl GameResult {
+    pub fn finished(board: &Board) -> Self {
+        let black = board.count_black();
+        let white = board.count_white();
+        let empties = 64 - black - white;
+        let (winner, black_discs, white_discs) = if black > white {
+            (Some(Player::Black), black + empties, white)
+        } else if white > black {
+            (Some(Player::White), black, white + empties)
+        } else {
+            (None, 32, 32)
+        };
+        GameResult {
+            winner: winner,
+            black_discs: black_discs,
+            white_discs: white_discs,
+            reason: ResultReason::Normal,
+        }
+    }
+
+    pub fn lost(board: &Board, loser: Player, reason: ResultReason) -> Self {
+        let empties = 64 - board.count_pieces();
+        let (winner, winner_discs) = match loser {
+            Player::Black => (Player::White, board.count_white()),
+            Player::White => (Player::Black, board.count_black()),
+        };
+        let winner_discs = (winner_discs + empties).max(33);
+        let (black_discs, white_discs) = match winner {
+            Player::Black => (winner_discs, 64 - winner_discs),
+            Player::White => (64 - winner_discs, winner_discs),
+        };
+        GameResult {
+            winner: Some(winner),
+            black_discs: black_discs,
+            white_discs: white_discs,
+            reason: reason,
+        }
+    }
+
+    pub fn with_penalty(self, player: Player, discs: i32) -> Self {
+        // gives `discs` of the player to its opponent, which may change the winner
+        let discs = discs.min(self.discs(player));
+        let (black_discs, white_discs) = match player {
+            Player::Black => (self.black_discs - discs, self.white_discs + discs),
+            Player::White => (self.black_discs + discs, self.white_discs - discs),
+        };
+        let winner = if black_discs > white_discs {
+            Some(Player::Black)
+        } else if white_discs > black_discs {
+            Some(Player::White)
+        } else {
+            None
+        };
+        GameResult {
+            winner: winner,
+            black_discs: black_discs,
+            white_discs: white_discs,
+            reason: self.reason,
+        }
+    }
+
+    pub fn discs(&self, player: Player) -> i32 {
+        match player {
+            Player::Black => self.black_discs,
+            Player::White => self.white_discs,
+        }
+    }
+
+    // the disc difference for black
+    pub fn margin(&self) -> i32 {
+        self.black_discs - self.white_discs
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self.reason {
+            ResultReason::Normal => "",
+            ResultReason::Timeout => " on time",
+            ResultReason::Resignation => " by resignation",
+        };
+        match self.winner {
+            None => write!(f, "Draw {}-{}", self.black_discs, self.white_discs),
+            Some(winner) => {
+                let name = if winner == Player::Black { "Black" } else { "White" };
+                let loser = if winner == Player::Black { Player::White } else { Player::Black };
+                write!(f, "{} wins{} {}-{}", name, reason, self.discs(winner), self.discs(loser))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::board::START_BOARD;
+
+    #[test]
+    fn test_scores() {
+        // 3 black discs, 1 white disc, 60 empty squares
+        let board = Board::new(0b111, 0b1000, None).unwrap();
+        let result = GameResult::finished(&board);
+        assert_eq!((result.winner, result.black_discs, result.white_discs), (Some(Player::Black), 63, 1));
+        assert_eq!(result.to_string(), "Black wins 63-1");
+
+        let draw = Board::new(0b11, 0b1100, None).unwrap();
+        assert_eq!(GameResult::finished(&draw).to_string(), "Draw 32-32");
+
+        let result = GameResult::lost(&START_BOARD, Player::Black, ResultReason::Timeout);
+        assert_eq!(result.to_string(), "White wins on time 62-2");
+        assert_eq!(result.margin(), -60);
+
+        // the winner gets at least 33 discs
+        let full = Board::new(u64::MAX >> 10, !(u64::MAX >> 10), Some(Player::White)).unwrap();
+        let result = GameResult::lost(&full, Player::Black, ResultReason::Resignation);
+        assert_eq!((result.black_discs, result.white_discs), (31, 33));
+
+        // a penalty can turn the game around
+        let result = GameResult::finished(&Board::new(0b111, 0b1000, None).unwrap()).with_penalty(Player::Black, 40);
+        assert_eq!(result.to_string(), "White wins 41-23");
+    }
+}
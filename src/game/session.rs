@@ -0,0 +1,507 @@
+// A game between two players, without any rendering.
+//
+// The session owns the board, the moves played so far, the clocks and the players. The player
+// to move thinks on its own thread; whoever drives the session (the window, the terminal, a
+// test) calls `tick` or `advance` regularly and gets back what happened since: moves, passes,
+// a player running out of time or forfeiting, and the end of the game.
+//
+// The clock of the player to move runs from the moment the previous move was made, and can be
+// paused. What happens when it reaches zero depends on the `TimeoutPolicy`: by default the
+// player loses on time, even if its move arrives later, and its thread is told to stop.
+
+use crate::ai::player::Player as AiPlayer;
+use crate::board::board::{Board, Ply, START_BOARD, Player, possible_plys, play};
+use crate::game::result::{GameResult, ResultReason};
+use crate::game::clock::{Clock, TimeControl};
+
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    Moved { player: Player, ply: Ply },
+    // the player had no legal move
+    Passed(Player),
+    // the clock of the player reached zero; the game only ends with it under `TimeoutPolicy::Lose`
+    TimedOut(Player),
+    // the player gave up, or answered with no move or an illegal one
+    Forfeited(Player),
+    GameOver,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SessionState {
+    Playing,
+    // played until neither side could move
+    Finished,
+    TimedOut(Player),
+    Forfeited(Player),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TimeoutPolicy {
+    // the player loses on time
+    Lose,
+    // the player keeps playing, and gives 2 discs to its opponent for every minute it started
+    // past its time
+    Penalty,
+    // the player keeps playing without a clock
+    Ignore,
+}
+
+// time a player out of time is given to think about each move, when it is not lost
+const OVERTIME_MOVE_TIME: Duration = Duration::from_secs(60);
+const PENALTY_DISCS_PER_MINUTE: i32 = 2;
+
+impl FromStr for TimeoutPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lose" => Ok(TimeoutPolicy::Lose),
+            "penalty" => Ok(TimeoutPolicy::Penalty),
+            "ignore" => Ok(TimeoutPolicy::Ignore),
+            _ => Err(format!("Invalid timeout policy: {}", s)),
+        }
+    }
+}
+
+pub struct GameSession<P: AiPlayer + Clone + Send + 'static> {
+    board: Board,
+    // the board before each move, and the move
+    history: Vec<(Board, Ply)>,
+    black: P,
+    white: P,
+    clock: Clock,
+    timeout_policy: TimeoutPolicy,
+    // the answer of the player thinking on its thread, and the flag that tells it to stop
+    thinking: Option<(Receiver<Option<Ply>>, Arc<AtomicBool>)>,
+    // whether the clock of black and of white running out was reported, until it has time again
+    timeout_reported: [bool; 2],
+    state: SessionState,
+}
+
+fn opponent(player: Player) -> Player {
+    match player {
+        Player::Black => Player::White,
+        Player::White => Player::Black,
+    }
+}
+
+impl<P: AiPlayer + Clone + Send + 'static> GameSession<P> {
+    pub fn new(black: P, white: P, black_control: TimeControl, white_control: TimeControl) -> Self {
+        let mut clock = Clock::new(black_control, white_control);
+        if let Some(turn) = START_BOARD.turn {
+            clock.start(turn);
+        }
+        let mut session = GameSession {
+            board: START_BOARD,
+            history: Vec::new(),
+            black: black,
+            white: white,
+            clock: clock,
+            timeout_policy: TimeoutPolicy::Lose,
+            thinking: None,
+            timeout_reported: [false; 2],
+            state: SessionState::Playing,
+        };
+        session.update_players();
+        session
+    }
+
+    pub fn with_timeout_policy(mut self, timeout_policy: TimeoutPolicy) -> Self {
+        self.timeout_policy = timeout_policy;
+        self
+    }
+
+    pub fn with_position(mut self, board: &Board) -> Self {
+        // Starts from `board` instead of the start position. A side to move without a move
+        // passes, and a position where neither side can move is a finished game.
+        self.board = board.clone();
+        self.board.pass_if_needed();
+        match self.board.turn {
+            Some(turn) => self.clock.start(turn),
+            None => {
+                self.clock.stop();
+                self.state = SessionState::Finished;
+            }
+        }
+        self.update_players();
+        self
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn history(&self) -> &[(Board, Ply)] {
+        &self.history
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    pub fn result(&self) -> Option<GameResult> {
+        // None while the game is going on
+        match self.state {
+            SessionState::Playing => None,
+            SessionState::Finished => {
+                let mut result = GameResult::finished(&self.board);
+                if self.timeout_policy == TimeoutPolicy::Penalty {
+                    for color in [Player::Black, Player::White] {
+                        result = result.with_penalty(color, self.penalty(color));
+                    }
+                }
+                Some(result)
+            }
+            SessionState::TimedOut(player) => Some(GameResult::lost(&self.board, player, ResultReason::Timeout)),
+            SessionState::Forfeited(player) => Some(GameResult::lost(&self.board, player, ResultReason::Resignation)),
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.state != SessionState::Playing
+    }
+
+    pub fn player(&self, color: Player) -> &P {
+        match color {
+            Player::Black => &self.black,
+            Player::White => &self.white,
+        }
+    }
+
+    pub fn current_player(&self) -> Option<&P> {
+        self.turn().map(|turn| self.player(turn))
+    }
+
+    fn turn(&self) -> Option<Player> {
+        // the player to move, None once the game is over
+        if self.is_over() {
+            return None;
+        }
+        self.board.turn
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn time_left(&self, color: Player) -> Duration {
+        self.clock.remaining(color)
+    }
+
+    pub fn overtime(&self, color: Player) -> Duration {
+        // how long the player has been playing without time left
+        self.clock.overtime(color)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
+
+    pub fn pause(&mut self) {
+        // stops the clock; a move made meanwhile is only played once the game resumes
+        self.clock.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.clock.resume();
+    }
+
+    pub fn penalty(&self, color: Player) -> i32 {
+        // discs the player gives away for its overtime under `TimeoutPolicy::Penalty`
+        let seconds = self.overtime(color).as_secs_f64();
+        PENALTY_DISCS_PER_MINUTE * (seconds / 60.0).ceil() as i32
+    }
+
+    fn stop_thinking(&mut self) {
+        if let Some((_, stop)) = self.thinking.take() {
+            stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn update_players(&mut self) {
+        self.black.update(&self.board);
+        self.white.update(&self.board);
+    }
+
+    fn end(&mut self, state: SessionState, events: &mut Vec<GameEvent>) {
+        // stops the clocks where they are, and the player thinking
+        self.clock.stop();
+        self.state = state;
+        self.stop_thinking();
+        match state {
+            SessionState::TimedOut(player) => events.push(GameEvent::TimedOut(player)),
+            SessionState::Forfeited(player) => events.push(GameEvent::Forfeited(player)),
+            SessionState::Finished | SessionState::Playing => {},
+        }
+        events.push(GameEvent::GameOver);
+    }
+
+    pub fn resign(&mut self, player: Player) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        if !self.is_over() {
+            self.end(SessionState::Forfeited(player), &mut events);
+        }
+        events
+    }
+
+    fn check_timeout(&mut self, turn: Player, events: &mut Vec<GameEvent>) -> bool {
+        // Reports the clock of the player to move running out, once until it has time again.
+        // True if that ended the game.
+        let index = if turn == Player::Black { 0 } else { 1 };
+        if !self.clock.is_flagged(turn) {
+            self.timeout_reported[index] = false;
+            return false;
+        }
+        if self.timeout_reported[index] {
+            return false;
+        }
+        self.timeout_reported[index] = true;
+        if self.timeout_policy == TimeoutPolicy::Lose {
+            self.end(SessionState::TimedOut(turn), events);
+            return true;
+        }
+        events.push(GameEvent::TimedOut(turn));
+        false
+    }
+
+    pub fn tick(&mut self) -> Vec<GameEvent> {
+        // what happened since the last call, without waiting
+        self.advance(Duration::ZERO)
+    }
+
+    pub fn advance(&mut self, wait: Duration) -> Vec<GameEvent> {
+        // Waits up to `wait` for the player to move, and returns what happened
+        let mut events = Vec::new();
+        let Some(turn) = self.turn() else {
+            return events;
+        };
+        if self.is_paused() {
+            return events;
+        }
+
+        // a clock can be out of time before the turn starts, with no base time or under a
+        // policy that plays on
+        if self.check_timeout(turn, &mut events) {
+            return events;
+        }
+        let time_left = self.time_left(turn);
+        let flagged = time_left.is_zero();
+        let (receiver, _) = self.thinking.get_or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel();
+            let stop = Arc::new(AtomicBool::new(false));
+            let player = if turn == Player::Black { self.black.clone() } else { self.white.clone() };
+            let board = self.board.clone();
+            let thinking_time = if flagged { OVERTIME_MOVE_TIME } else { time_left };
+            let player_stop = stop.clone();
+            thread::spawn(move || {
+                // the session may be gone by the time the answer comes
+                let _ = sender.send(player.generate_ply_or_stop(&board, thinking_time, &player_stop));
+            });
+            (receiver, stop)
+        });
+        // wake up when the clock runs out
+        let wait = if flagged { wait } else { wait.min(self.clock.time_to_flag(turn)) };
+        let answer = match receiver.recv_timeout(wait) {
+            Ok(answer) => Some(answer),
+            Err(RecvTimeoutError::Timeout) => None,
+            // the thread panicked
+            Err(RecvTimeoutError::Disconnected) => Some(None),
+        };
+
+        if self.check_timeout(turn, &mut events) {
+            return events;
+        }
+        let Some(answer) = answer else {
+            return events;
+        };
+        match answer {
+            Some(ply) if ply.is_in(possible_plys(&self.board)) => {
+                self.clock.finish_turn();
+                self.thinking = None;
+                self.history.push((self.board.clone(), ply));
+                self.board = play(&self.board, ply);
+                if let Some(next) = self.board.turn {
+                    self.clock.start(next);
+                }
+                self.update_players();
+
+                events.push(GameEvent::Moved { player: turn, ply: ply });
+                match self.board.turn {
+                    Some(next) if next == turn => events.push(GameEvent::Passed(opponent(turn))),
+                    Some(_) => {},
+                    None => self.end(SessionState::Finished, &mut events),
+                }
+            }
+            _ => self.end(SessionState::Forfeited(turn), &mut events),
+        }
+        events
+    }
+
+    pub fn run(&mut self) -> SessionState {
+        // plays the game out, waiting for the players
+        while !self.is_over() {
+            self.advance(Duration::MAX);
+        }
+        self.state
+    }
+}
+
+impl<P: AiPlayer + Clone + Send + 'static> Drop for GameSession<P> {
+    fn drop(&mut self) {
+        // a player still thinking has nobody left to answer to
+        self.stop_thinking();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::player::{AnyPlayer, RandomPlayer, GreedyPlayer};
+    use crate::board::board::{PARALLEL_START_BOARD, play_moves};
+
+    #[test]
+    fn test_full_games() {
+        for _ in 0..10 {
+            let mut session = GameSession::new(
+                AnyPlayer::Random(RandomPlayer),
+                AnyPlayer::Greedy(GreedyPlayer),
+                TimeControl::new(Duration::from_secs(10)),
+                TimeControl::new(Duration::from_secs(10)),
+            );
+            assert_eq!(session.run(), SessionState::Finished);
+            assert!(session.board().turn.is_none());
+            let result = session.result().unwrap();
+            assert_eq!(result.reason, ResultReason::Normal);
+            assert!(result.black_discs + result.white_discs == 64 || result.black_discs + result.white_discs == session.board().count_pieces());
+            assert!(session.time_left(Player::Black) < Duration::from_secs(10));
+
+            // the history replays to the final board
+            let mut board = START_BOARD;
+            for (before, ply) in session.history() {
+                assert!(*before == board);
+                board = play(&board, *ply);
+            }
+            assert!(board == *session.board());
+        }
+    }
+
+    #[derive(Clone)]
+    struct SlowPlayer {
+        delay: Duration,
+        // answers with no move at all
+        forfeit: bool,
+    }
+
+    impl AiPlayer for SlowPlayer {
+        fn generate_ply(&self, board: &Board, _time_left: Duration) -> Option<Ply> {
+            thread::sleep(self.delay);
+            if self.forfeit {
+                return None;
+            }
+            possible_plys(board).into_iter().next()
+        }
+    }
+
+    #[test]
+    fn test_with_position() {
+        let control = TimeControl::new(Duration::from_secs(60));
+        let board = play_moves(&PARALLEL_START_BOARD, "d6").unwrap();
+        let mut session = GameSession::new(AnyPlayer::Greedy(GreedyPlayer), AnyPlayer::Greedy(GreedyPlayer), control, control)
+            .with_position(&board);
+        assert!(session.clock().turn() == Some(Player::White));
+        assert_eq!(session.run(), SessionState::Finished);
+        assert!(session.history()[0].0 == board);
+
+        // a position where nobody can move is already over
+        let full = Board::new(u64::MAX, 0, Some(Player::White)).unwrap();
+        let session = GameSession::new(AnyPlayer::Greedy(GreedyPlayer), AnyPlayer::Greedy(GreedyPlayer), control, control)
+            .with_position(&full);
+        assert!(session.is_over());
+        assert_eq!(session.result().unwrap().black_discs, 64);
+    }
+
+    #[test]
+    fn test_timeout_and_forfeit() {
+        let slow = SlowPlayer { delay: Duration::from_millis(200), forfeit: false };
+        let mut session = GameSession::new(slow.clone(), slow, TimeControl::new(Duration::from_millis(50)), TimeControl::new(Duration::from_secs(1)));
+        assert_eq!(session.tick(), vec![]);
+        assert_eq!(session.run(), SessionState::TimedOut(Player::Black));
+        assert_eq!(session.result().unwrap().winner, Some(Player::White));
+        assert!(session.time_left(Player::Black).is_zero());
+        assert_eq!(session.time_left(Player::White), Duration::from_secs(1));
+
+        let forfeit = SlowPlayer { delay: Duration::ZERO, forfeit: true };
+        let mut session = GameSession::new(forfeit.clone(), forfeit, TimeControl::new(Duration::from_secs(1)), TimeControl::new(Duration::from_secs(1)));
+        let events = session.advance(Duration::from_secs(1));
+        assert_eq!(events, vec![GameEvent::Forfeited(Player::Black), GameEvent::GameOver]);
+        assert!(session.history().is_empty());
+    }
+
+    #[derive(Clone)]
+    struct StubbornPlayer {
+        // set once the player was told to stop
+        stopped: Arc<AtomicBool>,
+    }
+
+    impl AiPlayer for StubbornPlayer {
+        fn generate_ply(&self, _board: &Board, _time_left: Duration) -> Option<Ply> {
+            None
+        }
+
+        fn generate_ply_or_stop(&self, _board: &Board, _time_left: Duration, stop: &AtomicBool) -> Option<Ply> {
+            while !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            self.stopped.store(true, Ordering::Relaxed);
+            None
+        }
+    }
+
+    #[test]
+    fn test_timeout_policies() {
+        // the player that lost on time is stopped
+        let stubborn = StubbornPlayer { stopped: Arc::new(AtomicBool::new(false)) };
+        let mut session = GameSession::new(stubborn.clone(), stubborn.clone(), TimeControl::new(Duration::from_millis(20)), TimeControl::new(Duration::from_secs(1)));
+        assert_eq!(session.run(), SessionState::TimedOut(Player::Black));
+        thread::sleep(Duration::from_millis(50));
+        assert!(stubborn.stopped.load(Ordering::Relaxed));
+
+        // out of time, but the game goes on
+        let slow = SlowPlayer { delay: Duration::from_millis(30), forfeit: false };
+        let mut session = GameSession::new(slow.clone(), slow, TimeControl::new(Duration::from_millis(10)), TimeControl::new(Duration::from_secs(10)))
+            .with_timeout_policy(TimeoutPolicy::Penalty);
+        assert_eq!(session.advance(Duration::from_secs(1)), vec![GameEvent::TimedOut(Player::Black)]);
+        let events = session.advance(Duration::from_secs(1));
+        assert!(matches!(events[0], GameEvent::Moved { player: Player::Black, .. }));
+        assert!(session.overtime(Player::Black) >= Duration::from_millis(15));
+        assert_eq!(session.penalty(Player::Black), 2);
+        assert_eq!(session.penalty(Player::White), 0);
+        assert_eq!(session.state(), SessionState::Playing);
+    }
+
+    #[test]
+    fn test_no_time() {
+        // a clock with no time at all is out of time from the first tick
+        let control = TimeControl::new(Duration::ZERO);
+        let slow = SlowPlayer { delay: Duration::from_millis(10), forfeit: false };
+        let mut session = GameSession::new(slow.clone(), slow.clone(), control, control);
+        assert_eq!(session.tick(), vec![GameEvent::TimedOut(Player::Black), GameEvent::GameOver]);
+        assert_eq!(session.result().unwrap().reason, ResultReason::Timeout);
+
+        // and is reported once when the game goes on
+        let mut session = GameSession::new(slow.clone(), slow, control, TimeControl::new(Duration::from_secs(10)))
+            .with_timeout_policy(TimeoutPolicy::Ignore);
+        let events = session.advance(Duration::from_secs(1));
+        assert_eq!(events[0], GameEvent::TimedOut(Player::Black));
+        assert!(matches!(events[1], GameEvent::Moved { player: Player::Black, .. }));
+        assert!(matches!(session.advance(Duration::from_secs(1))[..], [GameEvent::Moved { player: Player::White, .. }]));
+        assert!(matches!(session.advance(Duration::from_secs(1))[..], [GameEvent::Moved { player: Player::Black, .. }]));
+    }
+}
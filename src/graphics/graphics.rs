@@ -1,5 +1,7 @@
 use macroquad::prelude::*;
 use crate::board::board::{Board, Piece, Ply, possible_plys, Player};
+use crate::game::result::GameResult;
+use crate::game::clock::format_clock;
 
 use std::time::Duration;
 
@@ -31,6 +33,24 @@ const SIDE_PANNEL_FONT_COLOR: Color = BLACK;
 const SIDE_PANNEL_FONT_SIZE: f32 = 160.0; // recomended to be 4x CIRCLE_RADIUS
 const SIDE_PANNEL_HEIGHT: f32 = 200.0;
 const SIDE_PANNEL_WIDTH: f32 = 450.0;
+const OPENING_FONT_SIZE: f32 = 50.0;
+
+// Result overlay parameters
+const RESULT_BOX_COLOR: Color = Color::new(0.15, 0.15, 0.15, 0.85);
+const RESULT_FONT_COLOR: Color = WHITE;
+const RESULT_FONT_SIZE: f32 = 80.0;
+const BUTTON_COLOR: Color = Color::new(0.50, 0.50, 0.50, 1.0);
+const BUTTON_FONT_SIZE: f32 = 40.0;
+const BUTTON_WIDTH: f32 = 180.0;
+const BUTTON_HEIGHT: f32 = 70.0;
+
+// What to do once a game is over
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResultAction {
+    Rematch,
+    SwapColours,
+    Quit,
+}
 
 pub fn detect_ply() -> Option<Ply> {
     if !is_mouse_button_pressed(MouseButton::Left) {
@@ -41,15 +61,14 @@ pub fn detect_ply() -> Option<Ply> {
     let (mouse_x, mouse_y) = mouse_position();
     for i in 0..8 {
         for j in 0..8 {
-            if MARGIN + i as f32 * SQUARE_SIZE <= mouse_x && mouse_x <= MARGIN + (i as f32 + 1.0) * SQUARE_SIZE {
-                if MARGIN + j as f32 * SQUARE_SIZE <= mouse_y && mouse_y <= MARGIN + (j as f32 + 1.0) * SQUARE_SIZE {
-                    row = Some(j);
-                    col = Some(i);
-                }
+            if MARGIN + i as f32 * SQUARE_SIZE <= mouse_x && mouse_x <= MARGIN + (i as f32 + 1.0) * SQUARE_SIZE
+                && MARGIN + j as f32 * SQUARE_SIZE <= mouse_y && mouse_y <= MARGIN + (j as f32 + 1.0) * SQUARE_SIZE {
+                row = Some(j);
+                col = Some(i);
             }
         }
     }
-    if row == None || col == None {
+    if row.is_none() || col.is_none() {
         return None;
     }
     let row = row.expect("row is None");
@@ -65,24 +84,20 @@ pub fn draw_timers(black_time: &Duration, white_time: &Duration, is_black_turn:
 }
 
 pub fn draw_time(x: f32, y: f32, time: &Duration, is_turn: bool, player: Player) {
-    let total_micros: i32 = time.as_micros().try_into().unwrap();
-
-    let seconds = total_micros / 1000000;
-    let minuites = seconds / 60;
-    let microseconds = total_micros % 1000000;
-    let seconds = seconds % 60;
+    let seconds = time.as_secs();
+    let centiseconds = time.subsec_millis() / 10;
     let circle_color = if player == Player::Black {BLACK_COLOR} else {WHITE_COLOR};
     let separator = if !is_turn {':'}
     else {
-        if seconds % 2 == 0 {':'} else {' '}
+        if seconds.is_multiple_of(2) {':'} else {' '}
     };
-    let time_string = if minuites == 0 && seconds < 10 {
-        format!("{}{}{}", seconds, separator, microseconds / 10000)
+    let time_string = if seconds < 10 {
+        format!("{}{}{:02}", seconds, separator, centiseconds)
     }
     else {
-        format!("{}{}{:02}", minuites, separator, seconds)
+        format_clock(*time).replace(':', &separator.to_string())
     };
-    let timer_box_color = if total_micros == 0 {
+    let timer_box_color = if time.is_zero() {
         TIMEOUT_COLOR
     }
     else {
@@ -111,7 +126,7 @@ fn draw_ply(ply: Ply, color: Color) {
     draw_circle(MARGIN + (col as f32 + 0.5) * SQUARE_SIZE, MARGIN + (row as f32 + 0.5) * SQUARE_SIZE, PLAYABLE_CIRCLE_RADIUS, color);
 }
 
-pub fn draw_side_pannel(board: &Board) {
+pub fn draw_side_pannel(board: &Board, opening: Option<&str>) {
     let top_left_x = 2.0 * MARGIN + 8.0 * SQUARE_SIZE;
     let top_left_y = MARGIN + 4.0 * SQUARE_SIZE - 0.5 * SIDE_PANNEL_HEIGHT;
     let n_black = format!("{}", board.count_black());
@@ -121,6 +136,10 @@ pub fn draw_side_pannel(board: &Board) {
     draw_circle(top_left_x + SQUARE_SIZE * 0.5, top_left_y + SIDE_PANNEL_HEIGHT - SQUARE_SIZE * 0.5, CIRCLE_RADIUS, WHITE_COLOR);
     draw_text(&n_black, top_left_x + SQUARE_SIZE, top_left_y + 0.5 * SQUARE_SIZE + CIRCLE_RADIUS, SIDE_PANNEL_FONT_SIZE, SIDE_PANNEL_FONT_COLOR);
     draw_text(&n_white, top_left_x + SQUARE_SIZE, top_left_y + SIDE_PANNEL_HEIGHT - 0.5 * SQUARE_SIZE + CIRCLE_RADIUS, SIDE_PANNEL_FONT_SIZE, SIDE_PANNEL_FONT_COLOR);
+    // the name of the opening under the panel
+    if let Some(opening) = opening {
+        draw_text(opening, top_left_x, top_left_y + SIDE_PANNEL_HEIGHT + OPENING_FONT_SIZE, OPENING_FONT_SIZE, SIDE_PANNEL_FONT_COLOR);
+    }
 }
 
 pub fn draw_board(board: &Board) {
@@ -142,7 +161,7 @@ fn draw_pieces(board: &Board) {
     for i in 0..8 {
         for j in 0..8 {
             let piece = board.get(i, j);
-            if piece == None {
+            if piece.is_none() {
                 continue;
             }
             if piece.expect("") == Piece::White {
@@ -154,3 +173,41 @@ fn draw_pieces(board: &Board) {
         }
     }
 }
+
+pub fn draw_result(result: &GameResult) -> Option<ResultAction> {
+    // Draws the result over the board with buttons for what to do next, and returns the
+    // button clicked or the key pressed (R, S, Q or escape)
+    let x = MARGIN + SQUARE_SIZE;
+    let y = MARGIN + 2.5 * SQUARE_SIZE;
+    draw_rectangle(x, y, 6.0 * SQUARE_SIZE, 3.0 * SQUARE_SIZE, RESULT_BOX_COLOR);
+    let text = result.to_string();
+    let size = measure_text(&text, None, RESULT_FONT_SIZE as u16, 1.0);
+    draw_text(&text, x + 3.0 * SQUARE_SIZE - size.width / 2.0, y + SQUARE_SIZE, RESULT_FONT_SIZE, RESULT_FONT_COLOR);
+
+    let buttons = [
+        (ResultAction::Rematch, "Rematch (R)", KeyCode::R),
+        (ResultAction::SwapColours, "Swap (S)", KeyCode::S),
+        (ResultAction::Quit, "Quit (Q)", KeyCode::Q),
+    ];
+    let gap = (6.0 * SQUARE_SIZE - 3.0 * BUTTON_WIDTH) / 4.0;
+    let button_y = y + 1.75 * SQUARE_SIZE;
+    let (mouse_x, mouse_y) = mouse_position();
+    let clicked = is_mouse_button_pressed(MouseButton::Left);
+    let mut action = None;
+    for (i, (button_action, label, key)) in buttons.into_iter().enumerate() {
+        let button_x = x + gap + i as f32 * (BUTTON_WIDTH + gap);
+        draw_rectangle(button_x, button_y, BUTTON_WIDTH, BUTTON_HEIGHT, BUTTON_COLOR);
+        let size = measure_text(label, None, BUTTON_FONT_SIZE as u16, 1.0);
+        draw_text(label, button_x + (BUTTON_WIDTH - size.width) / 2.0, button_y + BUTTON_HEIGHT / 2.0 + size.height / 2.0, BUTTON_FONT_SIZE, RESULT_FONT_COLOR);
+
+        let hovered = button_x <= mouse_x && mouse_x <= button_x + BUTTON_WIDTH
+            && button_y <= mouse_y && mouse_y <= button_y + BUTTON_HEIGHT;
+        if (clicked && hovered) || is_key_pressed(key) {
+            action = Some(button_action);
+        }
+    }
+    if is_key_pressed(KeyCode::Escape) {
+        action = Some(ResultAction::Quit);
+    }
+    action
+}
@@ -1,5 +1,8 @@
 pub mod board;
 pub mod ai;
+pub mod game;
+#[cfg(feature = "gui")]
 pub mod graphics;
 pub mod entrypoints;
+pub mod formats;
 
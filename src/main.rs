@@ -1,12 +1,17 @@
-use crate::ai::player::{MinMaxPlayer, HumanPlayer};
-use crate::board::board::Player;
+use revello::board::board::{Board, START_BOARD, play_moves, parse_moves, parse_position};
+use revello::ai::player::{AnyPlayer, MinMaxPlayer, HumanPlayer, RandomPlayer, GreedyPlayer};
+use revello::ai::external_engine::ExternalEnginePlayer;
+use revello::ai::level::{Level, Strength, MIN_LEVEL, MAX_LEVEL};
+use revello::ai::calibration::calibrate;
+use revello::ai::tournament::{MatchSettings, Sprt};
+use revello::formats::diagram::DEFAULT_SQUARE_SIZE;
+use revello::game::session::TimeoutPolicy;
+use revello::game::clock::TimeControl;
+use revello::ai::evaluator::{AnyEvaluator, HeuristicEvaluator, DiscCountEvaluator};
+use revello::ai::xot::{OpeningChoice, xot_openings, generate_xot, format_opening};
+use revello::ai::probcut::{ProbCut, Selectivity, parse_params, format_params, fit_probcut, random_positions};
 use std::time::Duration;
 
-mod board;
-mod ai;
-mod graphics;
-mod entrypoints;
-
 use clap::{Parser, Subcommand, Args};
 
 #[derive(Parser)]
@@ -22,53 +27,557 @@ enum Commands {
     /// Play the game
     Play(PlayOptions),
 
-    /// Analyse a game
-    Analyse {
-        /// Path to the game file to analyse
-        #[arg(short, long)]
+    /// Analyse the games of a GGF file move by move
+    Analyse(AnalyseOptions),
+
+    /// Fit ProbCut parameters on positions from random games
+    FitProbcut(FitProbcutOptions),
+
+    /// Generate XOT openings: 8 random moves the engine scores close to even
+    GenerateXot(GenerateXotOptions),
+
+    /// Play strength levels against the baseline players
+    Calibrate(CalibrateOptions),
+
+    /// Run as an engine speaking the NBoard protocol on stdin and stdout
+    Nboard(NboardOptions),
+
+    /// Run as an engine speaking GTP (Go Text Protocol) on stdin and stdout
+    Gtp(GtpOptions),
+
+    /// Play a match between two players without a window
+    Match(MatchOptions),
+
+    /// Solve the positions of an OBF file and check the moves and scores it gives
+    SolveSuite {
+        /// Path to the OBF file
+        file: String,
+    },
+
+    /// Convert between scoresheets (grids of move numbers) and GGF
+    Transcript {
+        #[command(subcommand)]
+        command: TranscriptCommands,
+    },
+
+    /// Draw a position or a game as an SVG or PNG image
+    ExportImage(ExportImageOptions),
+
+    /// Manage game databases
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum DbCommands {
+    /// Convert WTHOR databases (.wtb) into a GGF file, one game per line
+    Import(DbImportOptions),
+
+    /// Index games by position, from GGF files, WTHOR databases (.wtb) or scoresheets
+    Index(DbIndexOptions),
+
+    /// Show the games that reached a position, how they ended and what was played next
+    Query(DbQueryOptions),
+}
+
+#[derive(Subcommand)]
+enum TranscriptCommands {
+    /// Read a scoresheet and print the game as GGF
+    Import {
+        /// Path to the scoresheet
+        file: String,
+
+        /// Name of the black player
+        #[arg(long, default_value = "?")]
+        black: String,
+
+        /// Name of the white player
+        #[arg(long, default_value = "?")]
+        white: String,
+
+        /// Position the sheet starts from (see --position of play), instead of the usual one
+        #[arg(long, value_parser = parse_position, allow_hyphen_values = true)]
+        position: Option<Board>,
+    },
+
+    /// Print the scoresheet of every game of a GGF file
+    Export {
+        /// Path to the GGF file
         file: String,
     },
 }
 
+#[derive(Args)]
+struct DbImportOptions {
+    /// WTHOR game files
+    #[arg(required = true)]
+    files: Vec<String>,
+
+    /// Player names (WTHOR.JOU)
+    #[arg(long)]
+    players: Option<String>,
+
+    /// Tournament names (WTHOR.TRN)
+    #[arg(long)]
+    tournaments: Option<String>,
+
+    /// GGF file the games are written to
+    #[arg(long)]
+    output: String,
+}
+
+#[derive(Args)]
+struct ExportImageOptions {
+    /// Position to draw (see --position of play)
+    #[arg(long, value_parser = parse_position, default_value = "standard", allow_hyphen_values = true)]
+    position: Board,
+
+    /// Moves played from the position, like f5d6c3
+    #[arg(long, default_value = "")]
+    moves: String,
+
+    /// Draw a game instead: a GGF file, a WTHOR database (.wtb) or a scoresheet
+    #[arg(long, conflicts_with_all = ["position", "moves"])]
+    game: Option<String>,
+
+    /// Which game of the file, from 1
+    #[arg(long, default_value_t = 1)]
+    game_number: usize,
+
+    /// Stop the game after this many moves
+    #[arg(long)]
+    after: Option<usize>,
+
+    /// Mark the legal moves of the side to move
+    #[arg(long)]
+    legal_moves: bool,
+
+    /// Write the move numbers on the discs
+    #[arg(long)]
+    numbers: bool,
+
+    /// Size of a square in pixels
+    #[arg(long, default_value_t = DEFAULT_SQUARE_SIZE)]
+    square_size: u32,
+
+    /// Image to write, PNG if the name ends in .png and SVG otherwise
+    #[arg(long)]
+    output: String,
+}
+
+#[derive(Args)]
+struct DbIndexOptions {
+    /// Game files: .ggf, .wtb, or anything else for a scoresheet
+    #[arg(required = true)]
+    files: Vec<String>,
+
+    /// Player names for the WTHOR files (WTHOR.JOU)
+    #[arg(long)]
+    players: Option<String>,
+
+    /// File the index is written to
+    #[arg(long)]
+    output: String,
+}
+
+#[derive(Args)]
+struct DbQueryOptions {
+    /// Index written by db index
+    #[arg(long)]
+    db: String,
+
+    /// Position to look up (see --position of play)
+    #[arg(long, value_parser = parse_position, default_value = "standard", allow_hyphen_values = true)]
+    position: Board,
+
+    /// Moves played from the position, like f5d6c3
+    #[arg(long, default_value = "")]
+    moves: String,
+
+    /// Number of games to list
+    #[arg(long, default_value_t = 10)]
+    games: usize,
+}
+
 #[derive(Args)]
 struct PlayOptions {
-    /// Who plays as black (human, minmax)
+    /// Who plays as black (human, minmax, random, greedy, engine:<command>)
     #[arg(long)]
     black: PlayerType,
 
-    /// Who plays as white (human, minmax)
+    /// Who plays as white (human, minmax, random, greedy, engine:<command>)
     #[arg(long)]
     white: PlayerType,
 
-    /// Time for black (seconds)
-    #[arg(long, default_value_t = 300)]
-    black_time: u64,
+    /// Time control of both players, like 10m+5s (increment), 5m+d3s (delay) or 300 (seconds)
+    #[arg(long, default_value = "5m")]
+    time: TimeControl,
+
+    /// Time control of black, instead of --time
+    #[arg(long)]
+    black_time: Option<TimeControl>,
+
+    /// Time control of white, instead of --time
+    #[arg(long)]
+    white_time: Option<TimeControl>,
+
+    /// Evaluation used by a black minmax player (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    black_eval: EvaluatorType,
+
+    /// Evaluation used by a white minmax player (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    white_eval: EvaluatorType,
+
+    #[command(flatten)]
+    probcut: ProbCutOptions,
+
+    #[command(flatten)]
+    strength: StrengthOptions,
+
+    /// Position to start from: standard, parallel, or 64 squares row by row (X black, O white,
+    /// - empty) and the side to move, like ---------------------------OX------XO--------------------------- X
+    #[arg(long, value_parser = parse_position, default_value = "standard", allow_hyphen_values = true)]
+    position: Board,
+
+    /// Moves played from the position before the game starts, like f5d6c3
+    #[arg(long, default_value = "")]
+    moves: String,
+
+    /// Start from a bundled XOT opening instead: random, or its number
+    #[arg(long, conflicts_with = "position")]
+    xot: Option<OpeningChoice>,
+
+    /// What happens when a clock runs out (lose, penalty: 2 discs per minute over, ignore)
+    #[arg(long, default_value = "lose")]
+    on_timeout: TimeoutPolicy,
+
+    /// Where to play (window, tui)
+    #[arg(long, default_value = DEFAULT_UI)]
+    ui: UiType,
+}
+
+#[derive(Args)]
+struct AnalyseOptions {
+    /// Path to the GGF file to analyse
+    #[arg(short, long)]
+    file: String,
+
+    /// Search depth for every position
+    #[arg(long, default_value_t = 8)]
+    depth: u32,
+
+    /// Evaluation used by the search (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    eval: EvaluatorType,
+}
+
+#[derive(Args)]
+struct NboardOptions {
+    /// Evaluation used by the engine (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    eval: EvaluatorType,
+
+    /// Thinking time per move (seconds)
+    #[arg(long, default_value_t = 5.0)]
+    move_time: f64,
+
+    #[command(flatten)]
+    probcut: ProbCutOptions,
+
+    #[command(flatten)]
+    strength: StrengthOptions,
+}
+
+#[derive(Args)]
+struct GtpOptions {
+    /// Who generates the moves (minmax, random, greedy, engine:<command>)
+    #[arg(long, default_value = "minmax")]
+    player: PlayerType,
+
+    /// Evaluation used by a minmax player (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    eval: EvaluatorType,
 
-    /// Time for white (seconds)
+    /// Seconds per side when the front-end sets no time limit
     #[arg(long, default_value_t = 300)]
-    white_time: u64,
+    default_time: u64,
+
+    #[command(flatten)]
+    probcut: ProbCutOptions,
+
+    #[command(flatten)]
+    strength: StrengthOptions,
+}
+
+#[derive(Args)]
+struct MatchOptions {
+    /// The player the results are reported for (minmax, random, greedy, engine:<command>)
+    #[arg(long)]
+    first: PlayerType,
+
+    /// Its opponent (minmax, random, greedy, engine:<command>)
+    #[arg(long)]
+    second: PlayerType,
+
+    /// Evaluation used by the first player if it is minmax (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    first_eval: EvaluatorType,
+
+    /// Evaluation used by the second player if it is minmax (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    second_eval: EvaluatorType,
+
+    /// Strength of the first player if it is minmax, from 1 to 20
+    #[arg(long)]
+    first_level: Option<Level>,
+
+    /// Strength of the second player if it is minmax, from 1 to 20
+    #[arg(long)]
+    second_level: Option<Level>,
+
+    #[command(flatten)]
+    probcut: ProbCutOptions,
+
+    /// Number of games
+    #[arg(long, default_value_t = 100)]
+    games: u32,
+
+    /// Games played at the same time
+    #[arg(long, default_value_t = 1)]
+    concurrency: usize,
+
+    /// Time control of both players in every game, like 10s, 1m+1s (increment) or 1m+d1s (delay)
+    #[arg(long, default_value = "10s")]
+    time: TimeControl,
+
+    /// What happens when a clock runs out (lose, penalty: 2 discs per minute over, ignore)
+    #[arg(long, default_value = "lose")]
+    on_timeout: TimeoutPolicy,
+
+    /// File with one opening per line, as moves like f5d6c3 (positions 4 plys in if not given)
+    #[arg(long)]
+    openings: Option<String>,
+
+    /// Start every game from this position instead of the openings (see --position of play)
+    #[arg(long, value_parser = parse_position, conflicts_with = "openings", allow_hyphen_values = true)]
+    position: Option<Board>,
+
+    /// Play the bundled XOT openings, balanced positions 8 random moves in
+    #[arg(long, conflicts_with_all = ["openings", "position"])]
+    xot: bool,
+
+    /// Solve the game exactly once this many squares are empty (0 plays every game out)
+    #[arg(long, default_value_t = 0)]
+    adjudicate_empties: u32,
+
+    /// File the games are written to, one GGF game per line
+    #[arg(long)]
+    output: Option<String>,
+
+    /// Stop once a sequential probability ratio test decides between elo0 and elo1, like 0,10
+    #[arg(long)]
+    sprt: Option<Sprt>,
+}
+
+#[derive(Args)]
+struct StrengthOptions {
+    /// Strength of minmax players, from 1 (weakest) to 20 (full strength)
+    #[arg(long)]
+    level: Option<Level>,
+
+    /// Chance that a minmax player plays one of its next best moves instead of the best one
+    #[arg(long)]
+    blunder_probability: Option<f64>,
+}
+
+#[derive(Args)]
+struct CalibrateOptions {
+    /// Weakest level to calibrate
+    #[arg(long, default_value_t = MIN_LEVEL)]
+    min_level: u8,
+
+    /// Strongest level to calibrate
+    #[arg(long, default_value_t = MAX_LEVEL)]
+    max_level: u8,
+
+    /// Games against each baseline player
+    #[arg(long, default_value_t = 10)]
+    games: u32,
+
+    /// Time control of both players in every game, like 10s, 1m+1s (increment) or 1m+d1s (delay)
+    #[arg(long, default_value = "10s")]
+    time: TimeControl,
+}
+
+#[derive(Args)]
+struct ProbCutOptions {
+    /// How selective minmax players search, from 0 (full width) to 5 (most pruning)
+    #[arg(long, default_value_t = 0)]
+    selectivity: u8,
+
+    /// ProbCut parameters written by fit-probcut, instead of the built in ones
+    #[arg(long)]
+    probcut_params: Option<String>,
+}
+
+#[derive(Args)]
+struct GenerateXotOptions {
+    /// Number of openings
+    #[arg(long, default_value_t = 100)]
+    count: usize,
+
+    /// Search depth the openings are scored at
+    #[arg(long, default_value_t = 6)]
+    depth: u32,
+
+    /// Largest score, in discs either way, of a kept opening
+    #[arg(long, default_value_t = 1.5)]
+    max_eval: f32,
+
+    /// Seed for the random moves
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Evaluation used by the search (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    eval: EvaluatorType,
+
+    /// File to write the openings to, in the format of --openings (stdout if not given)
+    #[arg(long)]
+    output: Option<String>,
+}
+
+#[derive(Args)]
+struct FitProbcutOptions {
+    /// Number of positions to fit on
+    #[arg(long, default_value_t = 500)]
+    positions: usize,
+
+    /// Deepest search to fit parameters for, deeper searches are not cut
+    #[arg(long, default_value_t = 8)]
+    max_depth: u32,
+
+    /// Seed for the random games the positions are taken from
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Evaluation to fit the parameters for (heuristic, disc-count)
+    #[arg(long, default_value = "heuristic")]
+    eval: EvaluatorType,
+
+    /// File to write the parameters to (stdout if not given)
+    #[arg(long)]
+    output: Option<String>,
+}
+
+impl StrengthOptions {
+    fn build(&self) -> Result<Strength, String> {
+        let mut strength = self.level.map_or(Strength::FULL, |level| level.strength());
+        if let Some(p) = self.blunder_probability {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(format!("blunder probability must be between 0 and 1, got {}", p));
+            }
+            strength.blunder_probability = p;
+        }
+        Ok(strength)
+    }
+}
+
+impl ProbCutOptions {
+    fn build(&self) -> Result<ProbCut, String> {
+        let selectivity = Selectivity::new(self.selectivity)
+            .ok_or(format!("selectivity must be between 0 and {}", Selectivity::MAX_LEVEL))?;
+        match &self.probcut_params {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+                Ok(ProbCut::new(parse_params(&text)?, selectivity))
+            }
+            None => Ok(ProbCut::with_selectivity(selectivity)),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 enum PlayerType {
     Human,
     MinMax,
+    Random,
+    Greedy,
+    // an engine speaking the NBoard protocol, started with this command
+    Engine(String),
 }
 
 impl std::str::FromStr for PlayerType {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(command) = s.strip_prefix("engine:") {
+            return Ok(PlayerType::Engine(command.to_string()));
+        }
         match s.to_lowercase().as_str() {
             "human" => Ok(PlayerType::Human),
             "minmax" => Ok(PlayerType::MinMax),
+            "random" => Ok(PlayerType::Random),
+            "greedy" => Ok(PlayerType::Greedy),
             _ => Err(format!("Invalid player type: {}", s)),
         }
     }
 }
 
+const DEFAULT_UI: &str = if cfg!(feature = "gui") { "window" } else { "tui" };
+
+#[derive(Clone, Debug)]
+enum UiType {
+    Window,
+    Tui,
+}
+
+impl std::str::FromStr for UiType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "window" => Ok(UiType::Window),
+            "tui" => Ok(UiType::Tui),
+            _ => Err(format!("Invalid ui: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum EvaluatorType {
+    Heuristic,
+    DiscCount,
+}
+
+impl std::str::FromStr for EvaluatorType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "heuristic" => Ok(EvaluatorType::Heuristic),
+            "disc-count" => Ok(EvaluatorType::DiscCount),
+            _ => Err(format!("Invalid evaluator: {}", s)),
+        }
+    }
+}
+
+impl EvaluatorType {
+    fn build(&self) -> AnyEvaluator {
+        match self {
+            EvaluatorType::Heuristic => AnyEvaluator::Heuristic(HeuristicEvaluator),
+            EvaluatorType::DiscCount => AnyEvaluator::DiscCount(DiscCountEvaluator),
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
 use macroquad::prelude::Conf;
 
+#[cfg(feature = "gui")]
 fn window_conf() -> Conf {
     Conf {
         window_title: "Revello".to_owned(),
@@ -78,41 +587,265 @@ fn window_conf() -> Conf {
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
+fn build_player(player_type: &PlayerType, evaluator: &EvaluatorType, probcut: &ProbCut, strength: Strength) -> AnyPlayer {
+    match player_type {
+        PlayerType::Human => AnyPlayer::Human(HumanPlayer::new()),
+        PlayerType::MinMax => AnyPlayer::MinMax(
+            MinMaxPlayer::with_evaluator(evaluator.build())
+                .with_probcut(probcut.clone())
+                .with_strength(strength)
+        ),
+        PlayerType::Random => AnyPlayer::Random(RandomPlayer),
+        PlayerType::Greedy => AnyPlayer::Greedy(GreedyPlayer),
+        PlayerType::Engine(command) => AnyPlayer::External(exit_on_error(ExternalEnginePlayer::new(command))),
+    }
+}
+
+fn build_headless_player(player_type: &PlayerType, evaluator: &EvaluatorType, probcut: &ProbCut, strength: Strength) -> Result<AnyPlayer, String> {
+    // a player that needs no window and prints nothing
+    if let PlayerType::Human = player_type {
+        return Err("a human can only play with `play`".to_string());
+    }
+    match build_player(player_type, evaluator, probcut, strength) {
+        AnyPlayer::MinMax(player) => Ok(AnyPlayer::MinMax(player.quiet())),
+        player => Ok(player),
+    }
+}
+
+fn start_position(opts: &PlayOptions) -> Result<Board, String> {
+    // the position, or the XOT opening, and the moves played from it
+    let position = match opts.xot {
+        Some(choice) => {
+            let (number, board) = choice.pick(&xot_openings())?;
+            println!("XOT opening {}", number);
+            board
+        }
+        None => opts.position.clone(),
+    };
+    play_moves(&position, &opts.moves)
+}
+
+fn exit_on_error<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    })
+}
+
+#[cfg(feature = "gui")]
+async fn play(opts: PlayOptions) {
+    use revello::entrypoints::play::player_vs_player;
+
+    let black_time = opts.black_time.unwrap_or(opts.time);
+    let white_time = opts.white_time.unwrap_or(opts.time);
+    let probcut = exit_on_error(opts.probcut.build());
+    let strength = exit_on_error(opts.strength.build());
+
+    let black = build_player(&opts.black, &opts.black_eval, &probcut, strength);
+    let white = build_player(&opts.white, &opts.white_eval, &probcut, strength);
+    let black_name = player_name(&opts.black, opts.strength.level);
+    let white_name = player_name(&opts.white, opts.strength.level);
+    let start = exit_on_error(start_position(&opts));
+    player_vs_player(black, white, black_name, white_name, start, black_time, white_time, opts.on_timeout).await;
+}
+
+#[cfg(feature = "gui")]
+fn play_window(opts: PlayOptions) {
+    macroquad::Window::from_config(window_conf(), play(opts));
+}
+
+#[cfg(not(feature = "gui"))]
+fn play_window(_opts: PlayOptions) {
+    exit_on_error::<()>(Err("revello was built without the gui feature, play with --ui tui".to_string()));
+}
+
+fn player_name(player_type: &PlayerType, level: Option<Level>) -> String {
+    let name = match player_type {
+        PlayerType::Human => "human".to_string(),
+        PlayerType::MinMax => "minmax".to_string(),
+        PlayerType::Random => "random".to_string(),
+        PlayerType::Greedy => "greedy".to_string(),
+        PlayerType::Engine(command) => command.clone(),
+    };
+    match (player_type, level) {
+        (PlayerType::MinMax, Some(level)) => format!("{} {}", name, level),
+        _ => name,
+    }
+}
+
+fn play_tui(opts: PlayOptions) {
+    use revello::entrypoints::tui::run_tui;
+
+    let probcut = exit_on_error(opts.probcut.build());
+    let strength = exit_on_error(opts.strength.build());
+    // humans type their moves into the terminal, engines print their search information
+    let black = build_player(&opts.black, &opts.black_eval, &probcut, strength);
+    let white = build_player(&opts.white, &opts.white_eval, &probcut, strength);
+    let start = exit_on_error(start_position(&opts));
+
+    let stdin = std::io::stdin();
+    run_tui(
+        black,
+        white,
+        &start,
+        opts.black_time.unwrap_or(opts.time),
+        opts.white_time.unwrap_or(opts.time),
+        opts.on_timeout,
+        stdin.lock(),
+        std::io::stdout(),
+    ).expect("could not use the terminal");
+}
+
+fn main() {
     let cli = Cli::parse();
-    use crate::entrypoints::play::player_vs_player;
 
     match cli.command {
-        Commands::Play(opts) => {
-            let black_time = Duration::from_secs(opts.black_time);
-            let white_time = Duration::from_secs(opts.white_time);
-
-            match (opts.black, opts.white) {
-                (PlayerType::Human, PlayerType::Human) => {
-                    let mut black = HumanPlayer::new(Player::Black);
-                    let mut white = HumanPlayer::new(Player::White);
-                    player_vs_player(&mut black, &mut white, black_time, white_time).await;
-                }
-                (PlayerType::Human, PlayerType::MinMax) => {
-                    let mut black = HumanPlayer::new(Player::Black);
-                    let mut white = MinMaxPlayer::new();
-                    player_vs_player(&mut black, &mut white, black_time, white_time).await;
-                }
-                (PlayerType::MinMax, PlayerType::Human) => {
-                    let mut black = MinMaxPlayer::new();
-                    let mut white = HumanPlayer::new(Player::White);
-                    player_vs_player(&mut black, &mut white, black_time, white_time).await;
-                }
-                (PlayerType::MinMax, PlayerType::MinMax) => {
-                    let mut black = MinMaxPlayer::new();
-                    let mut white = MinMaxPlayer::new();
-                    player_vs_player(&mut black, &mut white, black_time, white_time).await;
-                }
+        Commands::Play(opts) => match opts.ui {
+            // only the game itself needs a window
+            UiType::Window => play_window(opts),
+            UiType::Tui => play_tui(opts),
+        },
+        Commands::Analyse(opts) => {
+            use revello::entrypoints::analyse::analyse_file;
+
+            let player = MinMaxPlayer::with_evaluator(opts.eval.build());
+            exit_on_error(analyse_file(&opts.file, &player, opts.depth, &mut std::io::stdout()));
+        }
+        Commands::Calibrate(opts) => {
+            for level in opts.min_level..=opts.max_level {
+                let level = exit_on_error(Level::new(level).ok_or(format!("no such level: {}", level)));
+                let random = calibrate(level, &AnyPlayer::Random(RandomPlayer), opts.games, opts.time);
+                let greedy = calibrate(level, &AnyPlayer::Greedy(GreedyPlayer), opts.games, opts.time);
+                println!("{:>8}: vs random {}, vs greedy {}", level, random, greedy);
+            }
+        }
+        Commands::Nboard(opts) => {
+            use revello::entrypoints::nboard::run_nboard;
+
+            let player = MinMaxPlayer::with_evaluator(opts.eval.build())
+                .with_probcut(exit_on_error(opts.probcut.build()))
+                .with_strength(exit_on_error(opts.strength.build()));
+            let move_time = exit_on_error(Duration::try_from_secs_f64(opts.move_time).map_err(|e| format!("invalid move time: {}", e)));
+            let stdin = std::io::stdin();
+            run_nboard(player, move_time, stdin.lock(), std::io::stdout()).expect("could not talk to the GUI");
+        }
+        Commands::Gtp(opts) => {
+            use revello::entrypoints::gtp::run_gtp;
+
+            let probcut = exit_on_error(opts.probcut.build());
+            let strength = exit_on_error(opts.strength.build());
+            let player = exit_on_error(build_headless_player(&opts.player, &opts.eval, &probcut, strength));
+            let stdin = std::io::stdin();
+            run_gtp(player, Duration::from_secs(opts.default_time), stdin.lock(), std::io::stdout()).expect("could not talk to the front-end");
+        }
+        Commands::Match(opts) => {
+            use revello::entrypoints::matches::{match_openings, run_match_report};
+
+            let probcut = exit_on_error(opts.probcut.build());
+            let first_strength = opts.first_level.map_or(Strength::FULL, |level| level.strength());
+            let second_strength = opts.second_level.map_or(Strength::FULL, |level| level.strength());
+            // fail early rather than on every thread
+            exit_on_error(build_headless_player(&opts.first, &opts.first_eval, &probcut, first_strength));
+            exit_on_error(build_headless_player(&opts.second, &opts.second_eval, &probcut, second_strength));
+
+            let openings = exit_on_error(match_openings(opts.position.as_ref(), opts.xot, opts.openings.as_deref()));
+            let games_output = opts.output.as_ref().map(|path| {
+                exit_on_error(std::fs::File::create(path).map_err(|e| format!("{}: {}", path, e)))
+            });
+            let settings = MatchSettings {
+                games: opts.games,
+                concurrency: opts.concurrency,
+                time: opts.time,
+                timeout_policy: opts.on_timeout,
+                openings: openings,
+                adjudicate_empties: opts.adjudicate_empties,
+                sprt: opts.sprt,
+            };
+            let first_name = player_name(&opts.first, opts.first_level);
+            let second_name = player_name(&opts.second, opts.second_level);
+            run_match_report(
+                &settings,
+                (&first_name, &second_name),
+                || build_headless_player(&opts.first, &opts.first_eval, &probcut, first_strength).unwrap(),
+                || build_headless_player(&opts.second, &opts.second_eval, &probcut, second_strength).unwrap(),
+                &mut std::io::stdout(),
+                games_output,
+            ).expect("could not write the results");
+        }
+        Commands::SolveSuite { file } => {
+            use revello::entrypoints::solve_suite::run_suite_file;
+
+            let result = exit_on_error(run_suite_file(&file, &mut std::io::stdout()));
+            if result.correct < result.checked {
+                std::process::exit(1);
             }
         }
-        Commands::Analyse { file } => {
-            println!("Analysing file: {}", file);
+        Commands::Transcript { command: TranscriptCommands::Import { file, black, white, position } } => {
+            use revello::entrypoints::transcript::import_transcript;
+
+            let game = exit_on_error(import_transcript(&file, &position.unwrap_or(START_BOARD), &black, &white));
+            println!("{}", game);
+        }
+        Commands::Transcript { command: TranscriptCommands::Export { file } } => {
+            use revello::entrypoints::transcript::export_transcripts;
+
+            exit_on_error(export_transcripts(&file, &mut std::io::stdout()));
+        }
+        Commands::ExportImage(opts) => {
+            use revello::entrypoints::export_image::{game_moves, game_diagram, write_image};
+
+            // the moves of the game, or those played from the position
+            let (start, mut moves) = match &opts.game {
+                Some(path) => exit_on_error(game_moves(path, opts.game_number)),
+                None => (opts.position.clone(), exit_on_error(parse_moves(&opts.position, &opts.moves))),
+            };
+            moves.truncate(opts.after.unwrap_or(moves.len()));
+            let diagram = game_diagram(&start, &moves, opts.legal_moves, opts.numbers, opts.square_size);
+            exit_on_error(write_image(&diagram, &opts.output));
+        }
+        Commands::Db { command: DbCommands::Import(opts) } => {
+            use revello::entrypoints::db::{import_wthor, read_names};
+            use revello::formats::wthor::{PLAYER_NAME_SIZE, TOURNAMENT_NAME_SIZE};
+
+            let players = exit_on_error(read_names(opts.players.as_deref(), PLAYER_NAME_SIZE));
+            let tournaments = exit_on_error(read_names(opts.tournaments.as_deref(), TOURNAMENT_NAME_SIZE));
+            let mut output = exit_on_error(std::fs::File::create(&opts.output).map_err(|e| format!("{}: {}", opts.output, e)));
+            let (imported, skipped) = exit_on_error(import_wthor(&opts.files, &players, &tournaments, &mut output));
+            println!("Imported {} games into {}, skipped {}", imported, opts.output, skipped);
+        }
+        Commands::Db { command: DbCommands::Index(opts) } => {
+            use revello::entrypoints::db::{index_games, read_names};
+            use revello::formats::wthor::PLAYER_NAME_SIZE;
+
+            let players = exit_on_error(read_names(opts.players.as_deref(), PLAYER_NAME_SIZE));
+            let (db, skipped) = exit_on_error(index_games(&opts.files, &players));
+            exit_on_error(std::fs::write(&opts.output, db.to_bytes()).map_err(|e| format!("{}: {}", opts.output, e)));
+            println!("Indexed {} games, {} positions into {}, skipped {}", db.games.len(), db.positions(), opts.output, skipped);
+        }
+        Commands::Db { command: DbCommands::Query(opts) } => {
+            use revello::entrypoints::db::{read_db, write_query};
+
+            let db = exit_on_error(read_db(&opts.db));
+            let board = exit_on_error(play_moves(&opts.position, &opts.moves));
+            write_query(&db, &board, opts.games, &mut std::io::stdout()).expect("could not write the results");
+        }
+        Commands::GenerateXot(opts) => {
+            let player = MinMaxPlayer::with_evaluator(opts.eval.build());
+            let openings = generate_xot(opts.count, opts.depth, opts.max_eval, opts.seed, &player);
+            let text: String = openings.iter().map(|(moves, eval)| format_opening(moves, *eval) + "\n").collect();
+            match opts.output {
+                Some(path) => std::fs::write(&path, text).expect("could not write the openings"),
+                None => print!("{}", text),
+            }
+        }
+        Commands::FitProbcut(opts) => {
+            let positions = random_positions(opts.positions, opts.seed);
+            let params = fit_probcut(&positions, opts.max_depth, &opts.eval.build());
+            let text = format_params(&params);
+            match opts.output {
+                Some(path) => std::fs::write(&path, text).expect("could not write the parameters"),
+                None => print!("{}", text),
+            }
         }
     }
 }
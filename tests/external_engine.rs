@@ -0,0 +1,116 @@
+// Plays against engines run as child processes: revello's own NBoard mode, and scripted
+// engines that misbehave.
+
+use revello::ai::external_engine::ExternalEnginePlayer;
+use revello::ai::player::{AnyPlayer, Player, RandomPlayer};
+use revello::ai::tournament::{GameEnd, MatchSettings, PlayedGame, play_game, run_match};
+use revello::board::board::{Player as BoardPlayer, START_BOARD};
+use revello::game::clock::TimeControl;
+use revello::game::session::TimeoutPolicy;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn play_random(engine: &ExternalEnginePlayer, time: Duration) -> PlayedGame {
+    // the engine has black
+    let engine = AnyPlayer::External(engine.clone());
+    play_game(&engine, &AnyPlayer::Random(RandomPlayer), &START_BOARD, TimeControl::new(time), TimeoutPolicy::Lose, 0)
+}
+
+struct ScriptEngine {
+    path: PathBuf,
+}
+
+impl ScriptEngine {
+    // a shell script engine in the temporary directory, deleted when dropped
+    fn new(name: &str, script: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("revello-{}-{}.sh", name, std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        ScriptEngine { path: path }
+    }
+
+    fn command(&self) -> String {
+        format!("sh {}", self.path.display())
+    }
+}
+
+impl Drop for ScriptEngine {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+#[test]
+fn test_game_against_nboard_engine() {
+    let command = format!("{} nboard --move-time 0.05 --level 3", env!("CARGO_BIN_EXE_revello"));
+    let engine = ExternalEnginePlayer::new(&command).unwrap();
+    assert_eq!(engine.name(), "Revello");
+
+    let played = play_random(&engine, Duration::from_secs(30));
+    assert_eq!(played.end, GameEnd::Finished);
+}
+
+#[test]
+fn test_forfeits() {
+    // answers the handshake, then an illegal move
+    let illegal = ScriptEngine::new("illegal", "\
+while read command args; do
+    case $command in
+        ping) echo \"pong $args\" ;;
+        go) echo '=== A1' ;;
+    esac
+done
+");
+    let engine = ExternalEnginePlayer::new(&illegal.command()).unwrap();
+    assert!(engine.generate_ply(&START_BOARD, Duration::from_secs(5)).is_none());
+    // and keeps forfeiting once stopped
+    assert!(engine.generate_ply(&START_BOARD, Duration::from_secs(5)).is_none());
+    assert_eq!(play_random(&engine, Duration::from_secs(5)).end, GameEnd::Forfeit(BoardPlayer::Black));
+
+    // answers the handshake, then never moves
+    let silent = ScriptEngine::new("silent", "\
+while read command args; do
+    if [ \"$command\" = ping ]; then echo \"pong $args\"; fi
+done
+");
+    let engine = ExternalEnginePlayer::new(&silent.command()).unwrap();
+    assert!(engine.generate_ply(&START_BOARD, Duration::from_millis(200)).is_none());
+
+    // exits right away
+    assert!(ExternalEnginePlayer::new(&ScriptEngine::new("exits", "exit 0\n").command()).is_err());
+}
+
+#[test]
+fn test_engine_plays_after_a_forfeit() {
+    // plays an illegal move the first time it is run, then properly from its next game
+    let marker = std::env::temp_dir().join(format!("revello-once-{}", std::process::id()));
+    let once = ScriptEngine::new("once", &format!("\
+if [ -e {marker} ]; then exec {revello} nboard --move-time 0.05 --level 1; fi
+touch {marker}
+while read command args; do
+    case $command in
+        ping) echo \"pong $args\" ;;
+        go) echo '=== A1' ;;
+    esac
+done
+", marker = marker.display(), revello = env!("CARGO_BIN_EXE_revello")));
+
+    let settings = MatchSettings {
+        games: 2,
+        concurrency: 1,
+        time: TimeControl::new(Duration::from_secs(30)),
+        timeout_policy: TimeoutPolicy::Lose,
+        openings: vec![START_BOARD],
+        adjudicate_empties: 0,
+        sprt: None,
+    };
+    let mut ends = Vec::new();
+    run_match(
+        &settings,
+        || AnyPlayer::External(ExternalEnginePlayer::new(&once.command()).unwrap()),
+        || AnyPlayer::Random(RandomPlayer),
+        |_, played, _, _| ends.push(played.end),
+    );
+    // the engine has black in the first game and white in the second
+    assert_eq!(ends, vec![GameEnd::Forfeit(BoardPlayer::Black), GameEnd::Finished]);
+    std::fs::remove_file(marker).unwrap();
+}
@@ -0,0 +1,49 @@
+// Drives `revello gtp` with a scripted session on stdin.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_gtp_session() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_revello"))
+        .args(["gtp", "--level", "5"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("could not start the engine");
+
+    let commands = "\
+1 protocol_version
+2 name
+boardsize 8
+clear_board
+time_settings 60 0 0
+play b f5
+3 genmove w
+showboard
+undo
+undo
+4 undo
+final_score
+known_command genmove
+foo
+quit
+";
+    engine.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+    let output = engine.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    let responses: Vec<&str> = output.split("\n\n").filter(|response| !response.is_empty()).collect();
+    assert_eq!(responses[0], "=1 2");
+    assert_eq!(responses[1], "=2 Revello");
+    // white answers f5 with one of its three moves
+    assert!(["=3 f4", "=3 f6", "=3 d6"].contains(&responses[6]), "{}", responses[6]);
+    assert!(responses[7].starts_with("= \n   A B C D E F G H"));
+    assert!(responses[7].ends_with("Black (X) to move, X 3 O 3"));
+    assert_eq!(responses[10], "?4 cannot undo");
+    assert_eq!(responses[11], "= 0");
+    assert_eq!(responses[12], "= true");
+    assert_eq!(responses[13], "? unknown command");
+    assert_eq!(responses.len(), 15);
+}
@@ -0,0 +1,52 @@
+// Drives `revello nboard` through a pipe, the way a GUI would.
+
+use revello::board::board::{Ply, START_BOARD, possible_plys, play};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_nboard_session() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_revello"))
+        .args(["nboard", "--move-time", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("could not start the engine");
+
+    let commands = "\
+nboard 2
+set depth 3
+set game (;GM[Othello]PC[NBoard]PB[a]PW[b]TY[8]BO[8 -------- -------- -------- ---O*--- ---*O--- -------- -------- -------- *]B[F5]W[F6];)
+move E6
+ping 1
+go
+hint 2
+learn
+ping 2
+";
+    engine.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+    let output = engine.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = output.lines().filter(|line| !line.starts_with("status") && !line.starts_with("nodestats")).collect();
+    assert_eq!(lines[0], "set myname Revello");
+    assert_eq!(lines[1], "pong 1");
+
+    // white to move after f5 f6 e6
+    let answer = lines[2].strip_prefix("=== ").expect("no move");
+    let fields: Vec<&str> = answer.split('/').collect();
+    assert_eq!(fields.len(), 3);
+    let mut board = START_BOARD;
+    for notation in ["f5", "f6", "e6"] {
+        board = play(&board, Ply::from_notation(notation).unwrap());
+    }
+    assert!(Ply::from_notation(fields[0]).unwrap().is_in(possible_plys(&board)));
+    assert!(fields[1].parse::<f32>().is_ok());
+
+    assert!(lines[3].starts_with("search "));
+    assert!(lines[4].starts_with("search "));
+    assert!(lines[3].ends_with(" 0 3"));
+    assert_eq!(lines[5], "learned");
+    assert_eq!(lines[6], "pong 2");
+}
@@ -0,0 +1,16 @@
+// Runs `revello solve-suite` over the endgame positions in tests/data.
+
+use std::process::Command;
+
+#[test]
+fn test_endgame_suite() {
+    let output = Command::new(env!("CARGO_BIN_EXE_revello"))
+        .args(["solve-suite", concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/endgames.obf")])
+        .output()
+        .expect("could not start the engine");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success(), "{}", stdout);
+    assert_eq!(stdout.lines().filter(|line| line.contains(" ok ")).count(), 4);
+    assert!(stdout.lines().last().unwrap().starts_with("4/4 correct, 4 positions"));
+}